@@ -0,0 +1,169 @@
+//! Recognising the audio files the sidecar can decode, so anything else is
+//! turned away with a clear message instead of failing inside the decoder.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Extensions the sidecar can decode; also the names [`sniff`] gives them.
+/// Its loader reads WAV only, so other audio is named but turned away.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["wav"];
+
+/// Enough of the file to tell the formats below apart.
+const HEADER_LEN: u64 = 12;
+
+/// A file that isn't one of [`SUPPORTED_EXTENSIONS`], and what it looks like.
+#[derive(Debug)]
+pub struct UnsupportedFormat {
+    pub path: PathBuf,
+    pub detected: Detected,
+}
+
+/// What the start of an unsupported file turned out to be.
+#[derive(Debug)]
+pub enum Detected {
+    /// A recognised format, named by its usual extension.
+    Format(&'static str),
+    /// Supported audio data behind an extension the sidecar goes by, e.g. a
+    /// WAV named `.wave`.
+    Extension(&'static str, String),
+    /// Nothing recognisable.
+    Unknown,
+    /// The file couldn't be read.
+    Unreadable(io::Error),
+}
+
+impl fmt::Display for UnsupportedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_else(|| self.path.to_string_lossy());
+        write!(f, "{name} is not a supported audio file: ")?;
+        match &self.detected {
+            Detected::Format(format) => write!(f, "it looks like a {format} file")?,
+            Detected::Extension(format, extension) => write!(
+                f,
+                "it holds {format} audio but is named .{extension}; rename it to .{format}"
+            )?,
+            Detected::Unknown => write!(f, "its contents aren't a recognised format")?,
+            Detected::Unreadable(err) => write!(f, "it could not be read ({err})")?,
+        }
+        write!(f, ". Supported: {}", SUPPORTED_EXTENSIONS.join(", "))
+    }
+}
+
+impl From<UnsupportedFormat> for String {
+    fn from(err: UnsupportedFormat) -> Self {
+        err.to_string()
+    }
+}
+
+/// The format of `path` if it has a supported extension and its first bytes
+/// match a supported format. The two needn't agree: the decoder goes by the
+/// contents.
+pub fn is_supported_audio(path: &Path) -> Result<&'static str, UnsupportedFormat> {
+    let unsupported = |detected| UnsupportedFormat {
+        path: path.to_path_buf(),
+        detected,
+    };
+    let mut header = Vec::new();
+    std::fs::File::open(path)
+        .and_then(|file| file.take(HEADER_LEN).read_to_end(&mut header))
+        .map_err(|err| unsupported(Detected::Unreadable(err)))?;
+    let format = sniff(&header).ok_or_else(|| unsupported(Detected::Unknown))?;
+    if !SUPPORTED_EXTENSIONS.contains(&format) {
+        return Err(unsupported(Detected::Format(format)));
+    }
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if !SUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(unsupported(Detected::Extension(format, extension)));
+    }
+    Ok(format)
+}
+
+/// Names the format starting with `header`: one of [`SUPPORTED_EXTENSIONS`],
+/// another audio format, or a common file type people drop in by mistake.
+fn sniff(header: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+    let format = match header {
+        _ if at(0, b"RIFF") || at(0, b"RF64") || at(0, b"BW64") => match header.get(8..12)? {
+            b"WAVE" => "wav",
+            b"AVI " => "avi",
+            b"WEBP" => "webp",
+            _ => return None,
+        },
+        _ if at(0, b"fLaC") => "flac",
+        _ if at(0, b"OggS") => "ogg",
+        _ if at(0, b"ID3") => "mp3",
+        // An MPEG audio frame sync: eleven set bits.
+        [0xFF, second, ..] if second & 0xE0 == 0xE0 => "mp3",
+        // ISO base media: only the major brand tells audio from video and
+        // images. `mp42`/`isom` files can hold audio alone, but telling
+        // would mean reading their tracks.
+        _ if at(4, b"ftyp") => match header.get(8..12)? {
+            b"M4A " | b"M4B " => "m4a",
+            b"qt  " => "mov",
+            b"heic" | b"heix" | b"mif1" => "heic",
+            [b'3', b'g', ..] => "3gp",
+            _ => "mp4",
+        },
+        _ if at(0, b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")) => "aiff",
+        _ if at(0, b"%PDF") => "pdf",
+        _ if at(0, b"\x89PNG") => "png",
+        [0xFF, 0xD8, 0xFF, ..] => "jpeg",
+        _ if at(0, b"GIF8") => "gif",
+        _ if at(0, b"PK\x03\x04") => "zip",
+        _ => return None,
+    };
+    Some(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_supported_formats() {
+        assert_eq!(sniff(b"RIFF\x24\x00\x00\x00WAVEfmt "), Some("wav"));
+        assert_eq!(sniff(b"RF64\xFF\xFF\xFF\xFFWAVEds64"), Some("wav"));
+    }
+
+    #[test]
+    fn names_other_audio() {
+        assert_eq!(sniff(b"fLaC\x00\x00\x00\x22"), Some("flac"));
+        assert_eq!(sniff(b"OggS\x00\x02"), Some("ogg"));
+        assert_eq!(sniff(b"ID3\x04\x00"), Some("mp3"));
+        assert_eq!(sniff(&[0xFF, 0xFB, 0x90, 0x64]), Some("mp3"));
+        assert_eq!(sniff(b"\x00\x00\x00\x20ftypM4A "), Some("m4a"));
+        assert_eq!(sniff(b"\x00\x00\x00\x20ftypM4B "), Some("m4a"));
+    }
+
+    #[test]
+    fn refuses_other_iso_media() {
+        for header in [
+            &b"\x00\x00\x00\x14ftypqt  "[..],
+            b"\x00\x00\x00\x18ftypheic",
+            b"\x00\x00\x00\x18ftypisom",
+        ] {
+            let format = sniff(header).unwrap();
+            assert_ne!(format, "m4a");
+            assert!(!SUPPORTED_EXTENSIONS.contains(&format), "{format}");
+        }
+        assert_eq!(sniff(b"\x00\x00\x00\x14ftyp"), None);
+    }
+
+    #[test]
+    fn names_other_formats() {
+        assert_eq!(sniff(b"%PDF-1.7"), Some("pdf"));
+        assert_eq!(sniff(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("jpeg"));
+        assert_eq!(sniff(b"RIFF\x24\x00\x00\x00AVI LIST"), Some("avi"));
+        assert_eq!(sniff(b"FORM\x00\x00\x00\x00AIFF"), Some("aiff"));
+        assert_eq!(sniff(b"hello world"), None);
+        assert_eq!(sniff(b""), None);
+    }
+}
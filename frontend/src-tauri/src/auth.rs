@@ -0,0 +1,24 @@
+//! Per-spawn bearer token that keeps other local processes from driving
+//! the sidecar.
+
+use std::fmt::Write;
+
+/// Env var the sidecar reads its expected token from.
+pub const TOKEN_ENV: &str = "NOMEN_AUTH_TOKEN";
+
+const TOKEN_BYTES: usize = 32;
+
+/// A fresh random token, hex-encoded.
+pub fn generate_token() -> Result<String, getrandom::Error> {
+    random_hex::<TOKEN_BYTES>()
+}
+
+/// `N` random bytes, hex-encoded.
+pub fn random_hex<const N: usize>() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    }))
+}
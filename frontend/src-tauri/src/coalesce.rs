@@ -0,0 +1,122 @@
+//! Folding overlapping calls into one: the first caller does the work and
+//! the ones that arrive meanwhile wait for its result instead of repeating
+//! it, e.g. a restart button pressed several times in a row.
+
+use std::sync::{Condvar, Mutex, PoisonError};
+
+use crate::sync::LockExt;
+
+pub struct Coalesce<T> {
+    run: Mutex<Run<T>>,
+    finished: Condvar,
+}
+
+struct Run<T> {
+    running: bool,
+    /// Bumped whenever a run ends, so waiters can tell theirs is over.
+    generation: u64,
+    /// What the last run returned; `None` if it panicked.
+    result: Option<T>,
+}
+
+impl<T> Default for Coalesce<T> {
+    fn default() -> Self {
+        Self {
+            run: Mutex::new(Run {
+                running: false,
+                generation: 0,
+                result: None,
+            }),
+            finished: Condvar::new(),
+        }
+    }
+}
+
+/// Ends the run even if `work` panics, so waiters don't hang.
+struct Finish<'a, T> {
+    coalesce: &'a Coalesce<T>,
+    result: Option<T>,
+}
+
+impl<T> Drop for Finish<'_, T> {
+    fn drop(&mut self) {
+        let mut run = self.coalesce.run.lock_or_recover();
+        run.running = false;
+        run.generation += 1;
+        run.result = self.result.take();
+        self.coalesce.finished.notify_all();
+    }
+}
+
+impl<T: Clone> Coalesce<T> {
+    /// Runs `work`, or, if another call is already running it, waits and
+    /// returns that call's result.
+    pub fn run(&self, work: impl FnOnce() -> T) -> T {
+        let mut run = self.run.lock_or_recover();
+        if run.running {
+            let generation = run.generation;
+            run = self
+                .finished
+                .wait_while(run, |run| run.generation == generation)
+                .unwrap_or_else(PoisonError::into_inner);
+            if let Some(result) = run.result.clone() {
+                return result;
+            }
+            // The run we waited for panicked; take our own turn.
+            if run.running {
+                drop(run);
+                return self.run(work);
+            }
+        }
+        run.running = true;
+        drop(run);
+
+        let mut finish = Finish {
+            coalesce: self,
+            result: None,
+        };
+        let result = work();
+        finish.result = Some(result.clone());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn overlapping_calls_share_one_run() {
+        let coalesce = Coalesce::default();
+        let runs = AtomicU32::new(0);
+        let (started_tx, started_rx) = mpsc::channel();
+
+        let (first, second) = thread::scope(|scope| {
+            let first = scope.spawn(|| {
+                coalesce.run(|| {
+                    started_tx.send(()).unwrap();
+                    thread::sleep(Duration::from_millis(200));
+                    runs.fetch_add(1, Ordering::SeqCst) + 1
+                })
+            });
+            started_rx.recv().unwrap();
+            let second = scope.spawn(|| coalesce.run(|| runs.fetch_add(1, Ordering::SeqCst) + 1));
+            (first.join().unwrap(), second.join().unwrap())
+        });
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!((first, second), (1, 1));
+    }
+
+    #[test]
+    fn a_call_after_the_run_ended_runs_again() {
+        let coalesce = Coalesce::default();
+        assert_eq!(coalesce.run(|| 1), 1);
+        assert_eq!(coalesce.run(|| 2), 2);
+    }
+}
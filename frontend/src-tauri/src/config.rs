@@ -0,0 +1,407 @@
+//! Environment-variable knobs for the sidecar. Every knob falls back to a
+//! built-in default when unset or unparseable.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const DEFAULT_STARTUP_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_LOG_BUFFER_LINES: usize = 500;
+const DEFAULT_MAX_RESTART_FAILURES: u32 = 5;
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 1;
+const DEFAULT_MAX_QUEUED_REQUESTS: usize = 64;
+const DEFAULT_KILL_GRACE_MS: u64 = 3000;
+const DEFAULT_HANDSHAKE_MAX_LINES: usize = 200;
+/// Roughly what the sidecar needs with both models loaded.
+const DEFAULT_MIN_MEMORY_MB: u64 = 4096;
+
+/// Parses `name` from the environment, returning `None` when it is unset
+/// or not a valid `T`.
+fn parse_var<T: std::str::FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok()?.trim().parse().ok()
+}
+
+/// Reads `name`, treating an empty or all-whitespace value as unset.
+fn non_empty_var(name: &str) -> Option<String> {
+    let value = env::var(name).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Failed restarts in a row after which the monitor gives up
+/// (`NOMEN_MAX_RESTART_FAILURES`).
+pub fn max_restart_failures() -> u32 {
+    parse_var("NOMEN_MAX_RESTART_FAILURES").unwrap_or(DEFAULT_MAX_RESTART_FAILURES)
+}
+
+/// How long the sidecar may sit unused before it is stopped
+/// (`NOMEN_IDLE_SHUTDOWN_SECS`); `None` when unset or 0.
+pub fn idle_shutdown() -> Option<Duration> {
+    parse_var::<u64>("NOMEN_IDLE_SHUTDOWN_SECS")
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// How often to emit `sidecar-resource-usage` (`NOMEN_RESOURCE_USAGE_MS`);
+/// `None` (no sampling) when unset or 0.
+pub fn resource_usage_interval() -> Option<Duration> {
+    parse_var::<u64>("NOMEN_RESOURCE_USAGE_MS")
+        .filter(|ms| *ms > 0)
+        .map(Duration::from_millis)
+}
+
+/// Available RAM below which starting the sidecar comes with a warning
+/// (`NOMEN_MIN_MEMORY_MB`); 0 turns the check off.
+pub fn min_memory_mb() -> u64 {
+    parse_var("NOMEN_MIN_MEMORY_MB").unwrap_or(DEFAULT_MIN_MEMORY_MB)
+}
+
+/// How many `call_sidecar` requests may run at once
+/// (`NOMEN_MAX_CONCURRENT_REQUESTS`); the rest wait in line.
+pub fn max_concurrent_requests() -> usize {
+    parse_var("NOMEN_MAX_CONCURRENT_REQUESTS").unwrap_or(DEFAULT_MAX_CONCURRENT_REQUESTS)
+}
+
+/// How many `call_sidecar` requests may wait in line
+/// (`NOMEN_MAX_QUEUED_REQUESTS`) before new ones are turned away; 0 for no
+/// limit.
+pub fn max_queued_requests() -> usize {
+    parse_var("NOMEN_MAX_QUEUED_REQUESTS").unwrap_or(DEFAULT_MAX_QUEUED_REQUESTS)
+}
+
+/// How many stdout lines may come before the handshake
+/// (`NOMEN_HANDSHAKE_MAX_LINES`) until startup fails; 0 for no limit.
+pub fn handshake_max_lines() -> usize {
+    parse_var("NOMEN_HANDSHAKE_MAX_LINES").unwrap_or(DEFAULT_HANDSHAKE_MAX_LINES)
+}
+
+/// Extra headers for requests to the sidecar (`NOMEN_SIDECAR_HEADERS`), as
+/// a JSON object of names to values.
+pub fn sidecar_headers() -> Option<String> {
+    non_empty_var("NOMEN_SIDECAR_HEADERS")
+}
+
+/// Route the readiness probe polls (`NOMEN_HEALTH_PATH`), for sidecars that
+/// serve their health check elsewhere.
+pub fn health_path() -> Option<String> {
+    let path = non_empty_var("NOMEN_HEALTH_PATH")?;
+    Some(if path.starts_with('/') {
+        path
+    } else {
+        format!("/{path}")
+    })
+}
+
+/// Status codes the readiness probe accepts (`NOMEN_HEALTH_STATUS`), as a
+/// comma-separated list such as `200,204`.
+pub fn health_statuses() -> Option<Vec<u16>> {
+    let value = non_empty_var("NOMEN_HEALTH_STATUS")?;
+    let statuses = value
+        .split(',')
+        .map(|status| status.trim().parse().ok())
+        .collect::<Option<Vec<u16>>>()
+        .filter(|statuses| !statuses.is_empty());
+    if statuses.is_none() {
+        log::warn!("Ignoring invalid NOMEN_HEALTH_STATUS {value:?}");
+    }
+    statuses
+}
+
+/// Whether `name` is set to a truthy value (`1`, `true` or `yes`).
+fn flag_var(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| {
+        matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes"
+        )
+    })
+}
+
+/// Verbosity of the app log (`NOMEN_LOG_LEVEL`): `trace`, `debug`, `info`,
+/// `warn` or `error`.
+pub fn log_level() -> Option<log::LevelFilter> {
+    parse_var("NOMEN_LOG_LEVEL")
+}
+
+/// Whether to start the sidecar on its first use instead of at launch
+/// (`NOMEN_LAZY_SPAWN`).
+pub fn lazy_spawn() -> bool {
+    flag_var("NOMEN_LAZY_SPAWN")
+}
+
+/// Whether to start the sidecar from a clean environment
+/// (`NOMEN_CLEAN_ENV`) rather than the app's own.
+pub fn clean_env() -> bool {
+    flag_var("NOMEN_CLEAN_ENV")
+}
+
+/// Whether to pause the sidecar's background work while the window is
+/// unfocused (`NOMEN_PAUSE_ON_BLUR`).
+pub fn pause_on_blur() -> bool {
+    flag_var("NOMEN_PAUSE_ON_BLUR")
+}
+
+/// Whether to run without a sidecar (`NOMEN_DISABLE_SIDECAR`), for working
+/// on the UI alone.
+pub fn sidecar_disabled() -> bool {
+    flag_var("NOMEN_DISABLE_SIDECAR")
+}
+
+/// How long to wait for the sidecar to report its port
+/// (`NOMEN_SIDECAR_TIMEOUT_SECS`).
+pub fn startup_timeout() -> Duration {
+    Duration::from_secs(
+        parse_var("NOMEN_SIDECAR_TIMEOUT_SECS").unwrap_or(DEFAULT_STARTUP_TIMEOUT_SECS),
+    )
+}
+
+/// How long a signalled sidecar gets to exit before it is force-killed
+/// (`NOMEN_KILL_GRACE_MS`). Zero skips straight to the force kill.
+pub fn kill_grace() -> Duration {
+    Duration::from_millis(parse_var("NOMEN_KILL_GRACE_MS").unwrap_or(DEFAULT_KILL_GRACE_MS))
+}
+
+/// Port of a sidecar started outside the app (`NOMEN_SIDECAR_PORT`), e.g.
+/// under a debugger. When set, the app connects to it instead of spawning.
+pub fn external_port() -> Option<String> {
+    non_empty_var("NOMEN_SIDECAR_PORT")
+}
+
+/// Token an external sidecar was started with (`NOMEN_AUTH_TOKEN`). Spawned
+/// sidecars always get a fresh one instead.
+pub fn auth_token() -> Option<String> {
+    non_empty_var(crate::auth::TOKEN_ENV)
+}
+
+/// Env var naming a fixed port for the sidecar; also how it is passed on.
+pub const FIXED_PORT_ENV: &str = "NOMEN_SIDECAR_FIXED_PORT";
+
+/// Port the sidecar should listen on instead of picking its own
+/// (`NOMEN_SIDECAR_FIXED_PORT`), e.g. to match firewall rules.
+pub fn fixed_port() -> Option<u16> {
+    parse_var(FIXED_PORT_ENV)
+}
+
+/// Accelerator to tell the sidecar to use (`NOMEN_DEVICE`), skipping
+/// detection.
+pub fn device() -> Option<String> {
+    non_empty_var(crate::device::DEVICE_ENV)
+}
+
+/// Resource subdirectory holding the bundled sidecar (`NOMEN_SIDECAR_DIR`),
+/// for bundles that don't use the default layout.
+pub fn sidecar_dir() -> Option<String> {
+    non_empty_var("NOMEN_SIDECAR_DIR")
+}
+
+/// Repo root to launch the dev sidecar from (`NOMEN_PROJECT_ROOT`),
+/// overriding the search upward from the working directory.
+pub fn project_root() -> Option<PathBuf> {
+    env::var_os("NOMEN_PROJECT_ROOT")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// How many recent stdout lines to keep in memory
+/// (`NOMEN_LOG_BUFFER_LINES`).
+pub fn log_buffer_lines() -> usize {
+    parse_var("NOMEN_LOG_BUFFER_LINES").unwrap_or(DEFAULT_LOG_BUFFER_LINES)
+}
+
+/// Python module the dev sidecar runs (`NOMEN_SIDECAR_MODULE`), for trying
+/// an alternate entrypoint.
+pub fn sidecar_module() -> Option<String> {
+    non_empty_var("NOMEN_SIDECAR_MODULE")
+}
+
+/// Interpreter to run the dev sidecar with (`NOMEN_PYTHON`), taking
+/// precedence over `uv`.
+pub fn python() -> Option<String> {
+    non_empty_var("NOMEN_PYTHON")
+}
+
+/// Absolute path of the interpreter to run the dev sidecar with
+/// (`NOMEN_PYTHON_BIN`), used as is: neither `uv` nor PATH is consulted.
+pub fn python_bin() -> Option<PathBuf> {
+    non_empty_var("NOMEN_PYTHON_BIN").map(PathBuf::from)
+}
+
+/// Directory the sidecar log goes to instead of the app log dir
+/// (`NOMEN_LOG_DIR`), for portable installs. Relative paths are resolved
+/// against the executable's directory, so `logs` lands next to the app.
+pub fn log_dir() -> Option<PathBuf> {
+    let path = PathBuf::from(non_empty_var("NOMEN_LOG_DIR")?);
+    if path.is_absolute() {
+        return Some(path);
+    }
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    Some(exe_dir.map_or(path.clone(), |dir| dir.join(path)))
+}
+
+/// Working directory for the bundled sidecar (`NOMEN_SIDECAR_CWD`) instead
+/// of the directory holding its binary. Relative paths are resolved against
+/// the resource directory by the caller.
+pub fn sidecar_cwd() -> Option<PathBuf> {
+    non_empty_var("NOMEN_SIDECAR_CWD").map(PathBuf::from)
+}
+
+/// Settings file handed to the sidecar as `--config <path>`
+/// (`NOMEN_SIDECAR_CONFIG`). Relative paths are resolved against the app's
+/// working directory, since the dev sidecar runs from the repo root.
+pub fn sidecar_config() -> Option<PathBuf> {
+    let path = PathBuf::from(non_empty_var("NOMEN_SIDECAR_CONFIG")?);
+    if path.is_absolute() {
+        return Some(path);
+    }
+    Some(env::current_dir().map_or(path.clone(), |cwd| cwd.join(path)))
+}
+
+/// Directories audio may be sent to the sidecar from (`NOMEN_ALLOWED_DIRS`,
+/// separated like PATH); empty when any directory is allowed.
+pub fn allowed_dirs() -> Vec<PathBuf> {
+    env::var_os("NOMEN_ALLOWED_DIRS")
+        .map(|dirs| {
+            env::split_paths(&dirs)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Prefix of the legacy port line (`NOMEN_PORT_PREFIX`), for sidecar builds
+/// that print e.g. `NOMEN_PORT=` instead of `PORT=`.
+pub fn port_prefix() -> Option<String> {
+    non_empty_var("NOMEN_PORT_PREFIX")
+}
+
+/// When the monitor brings an exited sidecar back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Never; a crash is reported and the sidecar stays down for inspection.
+    Off,
+    /// Only after an unexpected exit (non-zero status or a signal).
+    OnCrash,
+    /// After any exit, including a clean one.
+    Always,
+}
+
+/// `NOMEN_RESTART_POLICY=off|on-crash|always`; `always` by default. Stops
+/// the app asks for (`shutdown_sidecar`, the idle timer) never restart.
+pub fn restart_policy() -> RestartPolicy {
+    match non_empty_var("NOMEN_RESTART_POLICY").as_deref() {
+        None | Some("always") => RestartPolicy::Always,
+        Some("on-crash") => RestartPolicy::OnCrash,
+        Some("off") => RestartPolicy::Off,
+        Some(other) => {
+            log::warn!("Unknown NOMEN_RESTART_POLICY {other:?}; using always");
+            RestartPolicy::Always
+        }
+    }
+}
+
+/// Extra arguments for the sidecar (`NOMEN_SIDECAR_ARGS`), either a JSON
+/// array of strings or a space-separated list where `"..."` / `'...'` group
+/// words. A malformed value is an error rather than being partly dropped.
+pub fn sidecar_args() -> Result<Vec<String>, String> {
+    let Some(value) = non_empty_var("NOMEN_SIDECAR_ARGS") else {
+        return Ok(Vec::new());
+    };
+    if value.starts_with('[') {
+        return serde_json::from_str(&value)
+            .map_err(|err| format!("NOMEN_SIDECAR_ARGS is not a JSON array of strings: {err}"));
+    }
+    split_args(&value)
+}
+
+fn split_args(value: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    for c in value.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if let Some(q) = quote {
+        return Err(format!("NOMEN_SIDECAR_ARGS has an unterminated {q} quote"));
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_whitespace() {
+        assert_eq!(
+            split_args("--threads  8\t-v").unwrap(),
+            ["--threads", "8", "-v"]
+        );
+    }
+
+    #[test]
+    fn quotes_group_words() {
+        assert_eq!(
+            split_args(r#"--model "large v2" --name 'my run'"#).unwrap(),
+            ["--model", "large v2", "--name", "my run"]
+        );
+    }
+
+    #[test]
+    fn a_quote_can_hold_the_other_kind() {
+        assert_eq!(
+            split_args(r#"--title "it's" --say 'a "hi"'"#).unwrap(),
+            ["--title", "it's", "--say", r#"a "hi""#]
+        );
+    }
+
+    #[test]
+    fn backslashes_are_kept_as_is() {
+        assert_eq!(
+            split_args(r"--models C:\nomen\models").unwrap(),
+            ["--models", r"C:\nomen\models"]
+        );
+    }
+
+    #[test]
+    fn empty_quotes_are_an_empty_arg() {
+        assert_eq!(
+            split_args(r#"--prefix "" -v"#).unwrap(),
+            ["--prefix", "", "-v"]
+        );
+    }
+
+    #[test]
+    fn empty_input_has_no_args() {
+        assert!(split_args("").unwrap().is_empty());
+        assert!(split_args("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn an_unterminated_quote_is_an_error() {
+        let err = split_args(r#"--model "large"#).unwrap_err();
+        assert!(err.contains("unterminated \" quote"), "{err}");
+    }
+}
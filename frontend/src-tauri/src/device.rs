@@ -0,0 +1,46 @@
+//! Best-effort choice of the accelerator the sidecar should prefer, passed
+//! to it as `NOMEN_DEVICE`.
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+use crate::{config, launcher};
+
+/// Env var carrying the preferred device to the sidecar.
+pub const DEVICE_ENV: &str = "NOMEN_DEVICE";
+
+/// Returned by `get_detected_device`.
+#[derive(Clone, Debug, Serialize)]
+pub struct DetectedDevice {
+    /// `cuda`, `mps` or `cpu` (or whatever `NOMEN_DEVICE` was set to).
+    pub device: String,
+    /// Whether `NOMEN_DEVICE` overrode detection.
+    pub overridden: bool,
+}
+
+/// The preferred device, detected once per app run.
+pub fn preferred() -> &'static DetectedDevice {
+    static DEVICE: OnceLock<DetectedDevice> = OnceLock::new();
+    DEVICE.get_or_init(|| match config::device() {
+        Some(device) => DetectedDevice {
+            device,
+            overridden: true,
+        },
+        None => DetectedDevice {
+            device: detect().to_string(),
+            overridden: false,
+        },
+    })
+}
+
+/// Anything that goes wrong here just means CPU.
+fn detect() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "mps"
+    } else if launcher::find_on_path("nvidia-smi").is_some() {
+        "cuda"
+    } else {
+        "cpu"
+    }
+}
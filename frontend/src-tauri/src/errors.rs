@@ -0,0 +1,244 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Reasons the Python sidecar could not be brought up.
+#[derive(Debug)]
+pub enum SidecarError {
+    /// The OS refused to start the launcher process.
+    SpawnFailed(io::Error),
+    /// The launcher program (`uv`, Python, or the bundled binary) was not found.
+    LauncherMissing(PathBuf),
+    /// The launcher program exists but we may not execute it.
+    LauncherNotExecutable(PathBuf),
+    /// `NOMEN_PYTHON_BIN` doesn't name a usable interpreter; `reason` says
+    /// why.
+    PythonBinInvalid { path: PathBuf, reason: &'static str },
+    /// Tauri couldn't resolve the app's resource directory.
+    ResourceDirUnavailable(String),
+    /// Dev mode couldn't find the repo's `pyproject.toml` from this path.
+    ProjectRootNotFound(PathBuf),
+    /// The bundled sidecar binary is missing from the resource directory;
+    /// holds every path that was tried.
+    BinaryNotFound(Vec<PathBuf>),
+    /// The bundled sidecar binary exists but is not marked executable.
+    BinaryNotExecutable(PathBuf),
+    /// The bundled sidecar binary doesn't match its shipped checksum.
+    BinaryCorrupt(PathBuf),
+    /// The sidecar didn't report its port and pass the readiness probe
+    /// within the startup timeout; holds the first lines of its stderr.
+    PortTimeout(Duration, Vec<String>),
+    /// The sidecar closed stdout (usually by exiting) before printing its
+    /// handshake; holds the first lines of its stderr.
+    PortNeverReported(Vec<String>),
+    /// The sidecar printed `NOMEN_HANDSHAKE_MAX_LINES` lines of stdout
+    /// without a handshake among them; holds those lines.
+    HandshakeNotFound(Vec<String>),
+    /// The sidecar printed a handshake line we could not parse; holds the line.
+    InvalidPort(String),
+    /// `NOMEN_SIDECAR_ARGS` could not be parsed.
+    InvalidArgs(String),
+    /// `NOMEN_SIDECAR_CONFIG` names a file that doesn't exist.
+    ConfigNotFound(PathBuf),
+    /// `NOMEN_SIDECAR_CWD` names a directory that doesn't exist.
+    CwdNotFound(PathBuf),
+    /// The sidecar's announced version is outside the supported range.
+    VersionMismatch { expected: String, got: String },
+    /// Every port tried for `NOMEN_SIDECAR_FIXED_PORT` was already in use.
+    PortUnavailable(Vec<u16>),
+    /// The sidecar also answered on a non-loopback address.
+    ExposedOnNetwork(SocketAddr),
+    /// `cancel_sidecar_startup` was called while the sidecar was starting.
+    StartupCancelled,
+    /// `NOMEN_DISABLE_SIDECAR` is set.
+    Disabled,
+}
+
+impl fmt::Display for SidecarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SpawnFailed(err) => write!(f, "Failed to spawn sidecar: {err}"),
+            Self::LauncherMissing(program) => {
+                write!(
+                    f,
+                    "Could not find {} to start the sidecar",
+                    program.display()
+                )
+            }
+            Self::LauncherNotExecutable(program) => write!(
+                f,
+                "Permission denied running {} to start the sidecar",
+                program.display()
+            ),
+            Self::PythonBinInvalid { path, reason } => {
+                write!(f, "NOMEN_PYTHON_BIN ({}) {reason}", path.display())
+            }
+            Self::ResourceDirUnavailable(reason) => {
+                write!(f, "Could not locate the app's resource directory: {reason}")
+            }
+            Self::ProjectRootNotFound(path) => write!(
+                f,
+                "Could not find project root (pyproject.toml) from {}",
+                path.display()
+            ),
+            Self::BinaryNotFound(paths) => {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "Sidecar binary not found (tried {})", paths.join(", "))
+            }
+            Self::BinaryNotExecutable(path) => {
+                write!(f, "Sidecar binary at {} is not executable", path.display())
+            }
+            Self::BinaryCorrupt(path) => write!(
+                f,
+                "Sidecar binary at {} is damaged (checksum mismatch)",
+                path.display()
+            ),
+            Self::PortTimeout(waited, stderr) => {
+                write!(
+                    f,
+                    "Sidecar did not become ready within {}s",
+                    waited.as_secs()
+                )?;
+                write_stderr(f, stderr)
+            }
+            Self::PortNeverReported(stderr) => {
+                write!(f, "Sidecar exited before reporting its port")?;
+                write_stderr(f, stderr)
+            }
+            Self::HandshakeNotFound(lines) => {
+                write!(
+                    f,
+                    "Sidecar printed {} lines without reporting its port",
+                    lines.len()
+                )?;
+                // The tail is usually where it went wrong; all of it would
+                // swamp the dialog.
+                let skip = lines.len().saturating_sub(HANDSHAKE_LINES_SHOWN);
+                if skip > 0 {
+                    write!(f, " (last {HANDSHAKE_LINES_SHOWN} shown)")?;
+                }
+                write_stderr(f, &lines[skip..])
+            }
+            Self::InvalidPort(value) => write!(f, "Sidecar printed an invalid port: {value:?}"),
+            Self::InvalidArgs(reason) => write!(f, "{reason}"),
+            Self::ConfigNotFound(path) => write!(
+                f,
+                "Sidecar config file {} (NOMEN_SIDECAR_CONFIG) does not exist",
+                path.display()
+            ),
+            Self::CwdNotFound(path) => write!(
+                f,
+                "Sidecar working directory {} (NOMEN_SIDECAR_CWD) does not exist",
+                path.display()
+            ),
+            Self::VersionMismatch { expected, got } => write!(
+                f,
+                "Sidecar version {got} is not compatible with this app (requires {expected})"
+            ),
+            Self::PortUnavailable(ports) => {
+                let ports: Vec<String> = ports.iter().map(u16::to_string).collect();
+                write!(f, "Sidecar ports already in use: {}", ports.join(", "))
+            }
+            Self::ExposedOnNetwork(addr) => write!(
+                f,
+                "Sidecar is reachable from the network at {addr}; it must only listen on loopback"
+            ),
+            Self::StartupCancelled => write!(f, "Sidecar startup was cancelled"),
+            Self::Disabled => write!(f, "Sidecar is disabled by NOMEN_DISABLE_SIDECAR"),
+        }
+    }
+}
+
+/// How much of `HandshakeNotFound`'s output its message quotes.
+const HANDSHAKE_LINES_SHOWN: usize = 20;
+
+/// Appends captured stderr lines, if any, to an error message.
+fn write_stderr(f: &mut fmt::Formatter<'_>, stderr: &[String]) -> fmt::Result {
+    if stderr.is_empty() {
+        return Ok(());
+    }
+    write!(f, ":\n{}", stderr.join("\n"))
+}
+
+impl SidecarError {
+    /// Classifies a failed `Command::spawn` of `program`.
+    pub fn from_spawn(program: &OsStr, err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => Self::LauncherMissing(PathBuf::from(program)),
+            io::ErrorKind::PermissionDenied => Self::LauncherNotExecutable(PathBuf::from(program)),
+            _ => Self::SpawnFailed(err),
+        }
+    }
+}
+
+impl std::error::Error for SidecarError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::SpawnFailed(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// Commands hand errors to the frontend as their display text.
+impl serde::Serialize for SidecarError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Why `get_sidecar_port` has no port to give, so the frontend can branch on
+/// `kind` instead of parsing messages.
+#[derive(Debug)]
+pub enum PortError {
+    /// Nothing has started the sidecar, or it was stopped and not yet
+    /// brought back.
+    NotStarted,
+    /// A spawn is in progress; `sidecar-ready` or `sidecar-restarted`
+    /// follows once it is up.
+    Starting,
+    /// The sidecar exited or failed to start, and nothing is bringing it
+    /// back; holds why.
+    Crashed(String),
+    /// `NOMEN_DISABLE_SIDECAR` is set.
+    Disabled,
+}
+
+impl PortError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotStarted => "not_started",
+            Self::Starting => "starting",
+            Self::Crashed(_) => "crashed",
+            Self::Disabled => "disabled",
+        }
+    }
+}
+
+impl fmt::Display for PortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotStarted => write!(f, "Sidecar is not running"),
+            Self::Starting => write!(f, "Sidecar is still starting"),
+            Self::Crashed(reason) => write!(f, "Sidecar is down: {reason}"),
+            Self::Disabled => write!(f, "Sidecar is disabled (NOMEN_DISABLE_SIDECAR)"),
+        }
+    }
+}
+
+impl std::error::Error for PortError {}
+
+// Sent as `{ kind, message }`.
+impl serde::Serialize for PortError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut error = serializer.serialize_struct("PortError", 2)?;
+        error.serialize_field("kind", self.kind())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
@@ -0,0 +1,293 @@
+//! The default sidecar and everything known about it, kept together so one
+//! lock covers the process, its handshake and its timing. Only the lifecycle
+//! paths take that lock; after every change the handle publishes a
+//! `Snapshot` that commands read instead, so they never wait behind a spawn
+//! or kill holding it.
+
+use std::process::{Child, ExitStatus};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::errors::SidecarError;
+use crate::handshake::Handshake;
+use crate::headers::DefaultHeaders;
+use crate::sync::LockExt;
+
+/// The latest `Snapshot` of a `SidecarHandle`. Its lock is only held to copy
+/// the snapshot in or out.
+pub type Published = Arc<Mutex<Snapshot>>;
+
+#[derive(Default)]
+pub struct SidecarHandle {
+    /// `None` for a sidecar started outside the app (`NOMEN_SIDECAR_PORT`).
+    child: Option<Child>,
+    /// What the running sidecar announced about itself; `None` while down.
+    handshake: Option<Handshake>,
+    started_at: Option<Instant>,
+    last_exit_code: Option<i32>,
+    /// Why the last spawn failed; cleared by the next one that succeeds.
+    last_error: Option<String>,
+    /// Whether `warmup_sidecar` has finished loading the models.
+    warmed_up: bool,
+    /// Model picked with `set_sidecar_model`; `None` means the default.
+    current_model: Option<String>,
+    /// Sent with the `/shutdown` request that stops the child.
+    headers: Arc<DefaultHeaders>,
+    published: Published,
+}
+
+/// Point-in-time copy of a `SidecarHandle`.
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    pub running: bool,
+    pub pid: Option<u32>,
+    pub handshake: Option<Handshake>,
+    pub started_at: Option<Instant>,
+    pub last_exit_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub warmed_up: bool,
+    pub current_model: Option<String>,
+}
+
+impl Snapshot {
+    pub fn port(&self) -> Option<u16> {
+        self.handshake.as_ref().map(|handshake| handshake.port)
+    }
+}
+
+impl SidecarHandle {
+    pub fn new(headers: Arc<DefaultHeaders>) -> Self {
+        Self {
+            headers,
+            ..Self::default()
+        }
+    }
+
+    /// Where the handle publishes its snapshots.
+    pub fn published(&self) -> Published {
+        Arc::clone(&self.published)
+    }
+
+    /// Brings a sidecar up and records it. If the current child is still
+    /// alive, it is kept rather than leaked next to a second one; use
+    /// `restart` to replace it.
+    pub fn spawn(&mut self, app_handle: &tauri::AppHandle) -> Result<u16, SidecarError> {
+        self.spawn_with(|| crate::spawn_sidecar(app_handle))
+    }
+
+    fn spawn_with(
+        &mut self,
+        launch: impl FnOnce() -> Result<(Option<Child>, Handshake), SidecarError>,
+    ) -> Result<u16, SidecarError> {
+        let spawned = self.launch(launch);
+        self.publish();
+        spawned
+    }
+
+    fn launch(
+        &mut self,
+        launch: impl FnOnce() -> Result<(Option<Child>, Handshake), SidecarError>,
+    ) -> Result<u16, SidecarError> {
+        let alive = self
+            .child
+            .as_mut()
+            .is_some_and(|child| matches!(child.try_wait(), Ok(None)));
+        if let Some(handshake) = self.handshake.as_ref().filter(|_| alive) {
+            log::warn!("Sidecar is already running, not spawning another");
+            return Ok(handshake.port);
+        }
+
+        let (child, handshake) = launch().inspect_err(|err| {
+            self.last_error = Some(err.to_string());
+        })?;
+        self.last_error = None;
+        let port = handshake.port;
+        self.child = child;
+        self.handshake = Some(handshake);
+        self.started_at = Some(Instant::now());
+        self.warmed_up = false;
+        self.current_model = None;
+        Ok(port)
+    }
+
+    /// Stops the child, if we own one, and forgets the running sidecar. It
+    /// is unpublished first, so nothing is sent to it while it stops.
+    pub fn kill(&mut self) {
+        let child = self.child.take();
+        let handshake = self.handshake.clone();
+        self.clear();
+        if let Some(mut child) = child {
+            crate::stop_sidecar(handshake.as_ref(), &self.headers, &mut child);
+        }
+    }
+
+    /// Kills the child's process tree without asking it to exit first, and
+    /// forgets the running sidecar.
+    #[cfg(debug_assertions)]
+    pub fn force_kill(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            crate::process::kill_process_tree(&mut child);
+        }
+        self.clear();
+    }
+
+    pub fn restart(&mut self, app_handle: &tauri::AppHandle) -> Result<u16, SidecarError> {
+        self.kill();
+        self.spawn(app_handle)
+    }
+
+    pub fn snapshot(&mut self) -> Snapshot {
+        let (running, pid) = match self.child.as_mut() {
+            Some(child) => (matches!(child.try_wait(), Ok(None)), Some(child.id())),
+            // An external sidecar has no child; trust the port we were given.
+            None => (self.handshake.is_some(), None),
+        };
+        Snapshot {
+            running,
+            pid,
+            handshake: self.handshake.clone(),
+            started_at: self.started_at,
+            last_exit_code: self.last_exit_code,
+            last_error: self.last_error.clone(),
+            warmed_up: self.warmed_up,
+            current_model: self.current_model.clone(),
+        }
+    }
+
+    /// Whether the app owns a sidecar process right now.
+    pub fn has_child(&self) -> bool {
+        self.child.is_some()
+    }
+
+    /// Returns how the child exited if it has, recording the exit and
+    /// forgetting the sidecar.
+    pub fn poll_exit(&mut self) -> Option<ExitStatus> {
+        let status = self.child.as_mut()?.try_wait().ok()??;
+        self.child = None;
+        self.last_exit_code = status.code();
+        self.clear();
+        Some(status)
+    }
+
+    /// Records the warm-up result, unless the sidecar it was for has since
+    /// been replaced (`token` identifies it).
+    pub fn set_warmed_up(&mut self, token: Option<&str>, ready: bool) {
+        if self.is_current(token) {
+            self.warmed_up = ready;
+            self.publish();
+        }
+    }
+
+    /// Records the model the sidecar reported, with the same guard as
+    /// `set_warmed_up`.
+    pub fn set_current_model(&mut self, token: Option<&str>, model: String) {
+        if self.is_current(token) {
+            self.current_model = Some(model);
+            self.publish();
+        }
+    }
+
+    fn is_current(&self, token: Option<&str>) -> bool {
+        self.handshake
+            .as_ref()
+            .is_some_and(|handshake| handshake.auth_token.as_deref() == token)
+    }
+
+    fn clear(&mut self) {
+        self.handshake = None;
+        self.started_at = None;
+        self.warmed_up = false;
+        self.current_model = None;
+        self.publish();
+    }
+
+    fn publish(&mut self) {
+        let snapshot = self.snapshot();
+        *self.published.lock_or_recover() = snapshot;
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::cell::Cell;
+    use std::process::Command;
+
+    use super::*;
+
+    fn launch(
+        program: &str,
+        launches: &Cell<u32>,
+    ) -> Result<(Option<Child>, Handshake), SidecarError> {
+        launches.set(launches.get() + 1);
+        let child = Command::new(program)
+            .arg("30")
+            .spawn()
+            .map_err(SidecarError::SpawnFailed)?;
+        Ok((Some(child), Handshake::from_port(1)))
+    }
+
+    fn reap(handle: &mut SidecarHandle) {
+        if let Some(mut child) = handle.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    #[test]
+    fn spawning_twice_keeps_the_live_child() {
+        let launches = Cell::new(0);
+        let mut handle = SidecarHandle::default();
+
+        handle.spawn_with(|| launch("sleep", &launches)).unwrap();
+        let pid = handle.snapshot().pid;
+        handle.spawn_with(|| launch("sleep", &launches)).unwrap();
+
+        assert_eq!(launches.get(), 1);
+        assert_eq!(handle.snapshot().pid, pid);
+        reap(&mut handle);
+    }
+
+    #[test]
+    fn spawning_after_the_child_exited_launches_again() {
+        let launches = Cell::new(0);
+        let mut handle = SidecarHandle::default();
+
+        handle.spawn_with(|| launch("true", &launches)).unwrap();
+        let _ = handle.child.as_mut().unwrap().wait();
+        handle.spawn_with(|| launch("sleep", &launches)).unwrap();
+
+        assert_eq!(launches.get(), 2);
+        assert!(handle.snapshot().running);
+        reap(&mut handle);
+    }
+
+    #[test]
+    fn a_failed_spawn_is_remembered_until_one_succeeds() {
+        let launches = Cell::new(0);
+        let mut handle = SidecarHandle::default();
+
+        let missing = "/nonexistent/nomen-sidecar";
+        assert!(handle.spawn_with(|| launch(missing, &launches)).is_err());
+        assert!(handle.snapshot().last_error.is_some());
+        handle.spawn_with(|| launch("sleep", &launches)).unwrap();
+
+        assert_eq!(handle.snapshot().last_error, None);
+        reap(&mut handle);
+    }
+
+    #[test]
+    fn the_published_snapshot_follows_the_handle() {
+        let launches = Cell::new(0);
+        let mut handle = SidecarHandle::default();
+        let published = handle.published();
+
+        handle.spawn_with(|| launch("true", &launches)).unwrap();
+        assert_eq!(published.lock_or_recover().port(), Some(1));
+        let _ = handle.child.as_mut().unwrap().wait();
+        handle.poll_exit().unwrap();
+
+        let snapshot = published.lock_or_recover().clone();
+        assert_eq!(snapshot.port(), None);
+        assert_eq!(snapshot.last_exit_code, Some(0));
+    }
+}
@@ -0,0 +1,282 @@
+//! The first line the sidecar prints on stdout, announcing where it listens.
+//!
+//! Current sidecars print a JSON object such as
+//! `{"port": 51234, "pid": 9981, "version": "0.1.0", "capabilities": [...],
+//! "languages": ["en"]}`; older ones print `PORT=<n>`, which is still
+//! accepted. Forks that print another prefix can name it in
+//! `NOMEN_PORT_PREFIX`.
+
+use std::fmt;
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::errors::SidecarError;
+
+/// Prefix of the legacy handshake line, unless `NOMEN_PORT_PREFIX` names
+/// another.
+pub const PORT_PREFIX: &str = "PORT=";
+
+/// Sidecar versions this build of the app can talk to.
+pub const REQUIRED_SIDECAR_VERSION: &str = ">=0.1.0, <0.2.0";
+
+/// Whether the sidecar came up, as reported in the handshake's `status`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandshakeStatus {
+    #[default]
+    Ready,
+    /// The requested fixed port was taken; the sidecar exits after saying so.
+    PortInUse,
+}
+
+/// How the running sidecar was brought up: from the repo checkout, as the
+/// bundled binary, or not by us at all (`NOMEN_SIDECAR_PORT`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchMode {
+    Dev,
+    Bundled,
+    External,
+}
+
+impl LaunchMode {
+    /// The mode a sidecar this build spawns runs in: dev builds run the
+    /// Python module, release builds the bundled binary.
+    pub fn spawned() -> Self {
+        if cfg!(debug_assertions) {
+            Self::Dev
+        } else {
+            Self::Bundled
+        }
+    }
+}
+
+impl fmt::Display for LaunchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Dev => "dev",
+            Self::Bundled => "bundled",
+            Self::External => "external",
+        })
+    }
+}
+
+/// The handshake as printed.
+#[derive(Deserialize)]
+struct RawHandshake {
+    #[serde(default)]
+    status: HandshakeStatus,
+    port: u16,
+    #[serde(default)]
+    pid: Option<u32>,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    capabilities: Vec<String>,
+    #[serde(default)]
+    languages: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Handshake {
+    pub status: HandshakeStatus,
+    pub port: u16,
+    pub pid: Option<u32>,
+    pub version: Option<String>,
+    pub model: Option<String>,
+    /// Optional features the sidecar build supports; empty if it didn't say.
+    pub capabilities: Vec<String>,
+    /// Language codes captions can be asked for; empty if it didn't say.
+    pub languages: Vec<String>,
+    /// Token the app handed the sidecar at spawn; never part of what it prints.
+    #[serde(skip)]
+    pub auth_token: Option<String>,
+    /// Set by the app, like `auth_token`; `LaunchMode::spawned` until
+    /// told otherwise.
+    #[serde(skip)]
+    pub launch_mode: LaunchMode,
+}
+
+impl Handshake {
+    /// A handshake carrying nothing but the port.
+    pub fn from_port(port: u16) -> Self {
+        Self {
+            status: HandshakeStatus::Ready,
+            port,
+            pid: None,
+            version: None,
+            model: None,
+            capabilities: Vec::new(),
+            languages: Vec::new(),
+            auth_token: None,
+            launch_mode: LaunchMode::spawned(),
+        }
+    }
+
+    /// Checks the announced version against `REQUIRED_SIDECAR_VERSION`.
+    /// Legacy sidecars that don't announce a version are let through.
+    pub fn check_version(&self) -> Result<(), SidecarError> {
+        let Some(got) = self.version.as_deref() else {
+            log::warn!("Sidecar did not report a version, skipping compatibility check");
+            return Ok(());
+        };
+        let required =
+            VersionReq::parse(REQUIRED_SIDECAR_VERSION).expect("REQUIRED_SIDECAR_VERSION is valid");
+        match Version::parse(got) {
+            Ok(version) if required.matches(&version) => Ok(()),
+            _ => Err(SidecarError::VersionMismatch {
+                expected: REQUIRED_SIDECAR_VERSION.to_string(),
+                got: got.to_string(),
+            }),
+        }
+    }
+}
+
+/// Parses `line` as a handshake. Returns `None` for ordinary output, and
+/// `Some(Err(line))` when the line is a handshake with an unusable port,
+/// including one announcing a Unix socket, which the app can't reach.
+pub fn parse(line: &str) -> Option<Result<Handshake, String>> {
+    parse_with_prefix(
+        line,
+        config::port_prefix().as_deref().unwrap_or(PORT_PREFIX),
+    )
+}
+
+/// `parse`, with `prefix` in place of the configured legacy prefix.
+fn parse_with_prefix(line: &str, prefix: &str) -> Option<Result<Handshake, String>> {
+    let line = line.trim();
+    if line.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        if value.get("socket").is_some() {
+            return Some(Err(line.to_string()));
+        }
+        value.get("port")?;
+        let Ok(raw) = serde_json::from_value::<RawHandshake>(value) else {
+            return Some(Err(line.to_string()));
+        };
+        return Some(Ok(Handshake {
+            status: raw.status,
+            port: raw.port,
+            pid: raw.pid,
+            version: raw.version,
+            model: raw.model,
+            capabilities: raw.capabilities,
+            languages: raw.languages,
+            auth_token: None,
+            launch_mode: LaunchMode::spawned(),
+        }));
+    }
+    let port = line.strip_prefix(prefix)?;
+    Some(
+        port.parse::<u16>()
+            .map(Handshake::from_port)
+            .map_err(|_| line.to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_default(line: &str) -> Option<Result<Handshake, String>> {
+        parse_with_prefix(line, PORT_PREFIX)
+    }
+
+    fn with_version(version: &str) -> Handshake {
+        Handshake {
+            version: Some(version.to_string()),
+            ..Handshake::from_port(1)
+        }
+    }
+
+    #[test]
+    fn parses_a_json_handshake() {
+        let line = r#"{"port": 51234, "pid": 9981, "version": "0.1.0", "capabilities": ["waveform"], "languages": ["en"]}"#;
+
+        let handshake = parse_default(line).unwrap().unwrap();
+
+        assert_eq!(handshake.port, 51234);
+        assert_eq!(handshake.status, HandshakeStatus::Ready);
+        assert_eq!(handshake.pid, Some(9981));
+        assert_eq!(handshake.version.as_deref(), Some("0.1.0"));
+        assert_eq!(handshake.capabilities, ["waveform"]);
+        assert_eq!(handshake.languages, ["en"]);
+    }
+
+    #[test]
+    fn a_json_socket_is_an_unusable_handshake() {
+        assert!(parse_default(r#"{"socket": "/tmp/nomen.sock"}"#)
+            .unwrap()
+            .is_err());
+        assert!(parse_default(r#"{"socket": "/tmp/nomen.sock", "port": 1}"#)
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn json_without_a_port_is_ordinary_output() {
+        assert!(parse_default(r#"{"event": "loading"}"#).is_none());
+    }
+
+    #[test]
+    fn json_with_a_bad_port_is_an_unusable_handshake() {
+        assert!(parse_default(r#"{"port": 70000}"#).unwrap().is_err());
+    }
+
+    #[test]
+    fn parses_a_legacy_port_line() {
+        let handshake = parse_default("  PORT=12345\n").unwrap().unwrap();
+
+        assert_eq!(handshake.port, 12345);
+        assert!(handshake.version.is_none());
+    }
+
+    #[test]
+    fn a_custom_prefix_replaces_the_legacy_one() {
+        let handshake = parse_with_prefix("NOMEN_PORT=4242", "NOMEN_PORT=")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(handshake.port, 4242);
+        assert!(parse_with_prefix("PORT=4242", "NOMEN_PORT=").is_none());
+    }
+
+    #[test]
+    fn a_garbage_port_is_an_unusable_handshake() {
+        assert_eq!(parse_default("PORT=abc").unwrap().unwrap_err(), "PORT=abc");
+    }
+
+    #[test]
+    fn other_lines_are_ordinary_output() {
+        assert!(parse_default("Loading model...").is_none());
+        assert!(parse_default("{not json").is_none());
+    }
+
+    #[test]
+    fn accepts_versions_inside_the_required_range() {
+        assert!(with_version("0.1.0").check_version().is_ok());
+        assert!(with_version("0.1.99").check_version().is_ok());
+    }
+
+    #[test]
+    fn rejects_versions_outside_the_required_range() {
+        for version in ["0.0.9", "0.2.0", "not-a-version"] {
+            assert!(
+                matches!(
+                    with_version(version).check_version(),
+                    Err(SidecarError::VersionMismatch { got, .. }) if got == version
+                ),
+                "{version}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_missing_version_is_let_through() {
+        assert!(Handshake::from_port(1).check_version().is_ok());
+    }
+}
@@ -0,0 +1,78 @@
+//! Extra headers sent with every proxied call, readiness probe and
+//! heartbeat, for deployments that put the sidecar behind something
+//! expecting them (a local auth proxy, say). Seeded from
+//! `NOMEN_SIDECAR_HEADERS` and changeable at runtime.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use tauri_plugin_http::reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+
+use crate::config;
+use crate::sync::LockExt;
+
+#[derive(Default)]
+pub struct DefaultHeaders {
+    headers: Mutex<BTreeMap<String, String>>,
+}
+
+impl DefaultHeaders {
+    /// The headers in `NOMEN_SIDECAR_HEADERS`, a JSON object of names to
+    /// string values. Entries that aren't valid headers are skipped with a
+    /// warning, and so is the whole variable if it isn't such an object.
+    pub fn from_env() -> Self {
+        let headers = Self::default();
+        let Some(raw) = config::sidecar_headers() else {
+            return headers;
+        };
+        let parsed: BTreeMap<String, String> = match serde_json::from_str(&raw) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                log::warn!(
+                    "Ignoring NOMEN_SIDECAR_HEADERS, expected a JSON object of strings: {err}"
+                );
+                return headers;
+            }
+        };
+        for (name, value) in parsed {
+            if let Err(err) = headers.set(&name, &value) {
+                log::warn!("Ignoring header {name:?} in NOMEN_SIDECAR_HEADERS: {err}");
+            }
+        }
+        headers
+    }
+
+    /// Adds `name` or replaces its value. `Authorization` is refused: it
+    /// carries the per-spawn token.
+    pub fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        let parsed =
+            HeaderName::try_from(name).map_err(|_| format!("Invalid header name {name:?}"))?;
+        if parsed == AUTHORIZATION {
+            return Err("Authorization is set by the app and can't be overridden".to_string());
+        }
+        HeaderValue::try_from(value).map_err(|_| format!("Invalid value for header {name}"))?;
+        // Names are case-insensitive; `HeaderName` lowercases them.
+        self.headers
+            .lock_or_recover()
+            .insert(parsed.as_str().to_string(), value.to_string());
+        Ok(())
+    }
+
+    pub fn clear(&self) {
+        self.headers.lock_or_recover().clear();
+    }
+
+    /// The headers as reqwest takes them.
+    pub fn header_map(&self) -> HeaderMap {
+        self.headers
+            .lock_or_recover()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::try_from(name.as_str()).ok()?,
+                    HeaderValue::try_from(value.as_str()).ok()?,
+                ))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,124 @@
+//! HTTP readiness checks and shutdown requests against the sidecar's
+//! FastAPI app.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::errors::SidecarError;
+use crate::headers::DefaultHeaders;
+use crate::http;
+
+/// Route polled to decide whether the sidecar is serving requests, unless
+/// `NOMEN_HEALTH_PATH` names another.
+pub const HEALTH_PATH: &str = "/health";
+/// Status the probe accepts, unless `NOMEN_HEALTH_STATUS` lists others.
+pub const HEALTH_STATUS: u16 = 200;
+/// Route that makes the sidecar finish in-flight work and exit.
+pub const SHUTDOWN_PATH: &str = "/shutdown";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One health probe; true only for an accepted status within `timeout`.
+pub fn is_healthy(
+    port: u16,
+    token: Option<&str>,
+    headers: &DefaultHeaders,
+    timeout: Duration,
+) -> bool {
+    let mut request = http::client()
+        .get(format!("http://127.0.0.1:{port}{}", health_path()))
+        .timeout(timeout)
+        .headers(headers.header_map());
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    tauri::async_runtime::block_on(async {
+        request
+            .send()
+            .await
+            .is_ok_and(|response| is_accepted(response.status().as_u16()))
+    })
+}
+
+/// The readiness route: `NOMEN_HEALTH_PATH`, or `/health`.
+pub fn health_path() -> String {
+    config::health_path().unwrap_or_else(|| HEALTH_PATH.to_string())
+}
+
+fn is_accepted(status: u16) -> bool {
+    config::health_statuses().map_or(status == HEALTH_STATUS, |statuses| {
+        statuses.contains(&status)
+    })
+}
+
+/// Polls the health route until it answers an accepted status or `deadline`
+/// passes. A sidecar may announce its port a moment before it accepts
+/// connections, so refused or failed probes just mean "not yet"; only the
+/// deadline (or `cancelled`) ends the wait. `timeout` is only used to
+/// describe the failure.
+pub fn wait_until_ready(
+    port: u16,
+    token: Option<&str>,
+    headers: &DefaultHeaders,
+    deadline: Instant,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Result<(), SidecarError> {
+    let url = format!("http://127.0.0.1:{port}{}", health_path());
+
+    tauri::async_runtime::block_on(async {
+        let mut refused = 0u32;
+        loop {
+            let mut request = http::client()
+                .get(&url)
+                .timeout(REQUEST_TIMEOUT)
+                .headers(headers.header_map());
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            let outcome = match request.send().await {
+                Ok(response) if is_accepted(response.status().as_u16()) => {
+                    if refused > 0 {
+                        log::debug!("Sidecar accepted connections after {refused} refused probes");
+                    }
+                    return Ok(());
+                }
+                Ok(response) => format!("answered {}", response.status()),
+                // Not listening yet.
+                Err(err) if err.is_connect() => {
+                    refused += 1;
+                    "refused the connection".to_string()
+                }
+                Err(err) => format!("failed: {err}"),
+            };
+            if cancelled.load(Ordering::SeqCst) {
+                return Err(SidecarError::StartupCancelled);
+            }
+            if Instant::now() >= deadline {
+                log::warn!("Sidecar never became ready; the last probe {outcome}");
+                return Err(SidecarError::PortTimeout(timeout, Vec::new()));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    })
+}
+
+/// Asks the sidecar to shut itself down via `SHUTDOWN_PATH`. Returns whether
+/// it accepted; the caller still has to wait for the process to exit.
+pub fn request_shutdown(port: u16, token: Option<&str>, headers: &DefaultHeaders) -> bool {
+    let mut request = http::client()
+        .post(format!("http://127.0.0.1:{port}{SHUTDOWN_PATH}"))
+        .timeout(REQUEST_TIMEOUT)
+        .headers(headers.header_map());
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    tauri::async_runtime::block_on(async {
+        request
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    })
+}
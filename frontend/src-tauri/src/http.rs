@@ -0,0 +1,24 @@
+//! The one HTTP client every request to the sidecar goes through, so
+//! back-to-back calls reuse a kept-alive loopback connection instead of
+//! dialling a new one each time. Timeouts are set per request, and so is the
+//! auth token, which changes with every spawn.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tauri_plugin_http::reqwest;
+
+/// Loopback connects either succeed at once or are refused.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// The shared client, built on first use.
+pub fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
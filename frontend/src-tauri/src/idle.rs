@@ -0,0 +1,174 @@
+//! Optional idle shutdown (`NOMEN_IDLE_SHUTDOWN_SECS`): stops the sidecar
+//! after a stretch without requests so it gives back its RAM, and brings it
+//! back on the next one. Lazy spawn (`NOMEN_LAZY_SPAWN`) reuses the same
+//! path: the sidecar starts out stopped and the first request brings it up,
+//! as does `shutdown_sidecar`, which stops it until the next request.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::{Emitter, Manager};
+
+use crate::errors::SidecarError;
+use crate::sync::LockExt;
+use crate::{SidecarReady, SidecarState};
+
+/// Bounds on how often the watcher checks for idleness.
+const MIN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// When the sidecar was last used, and whether it is down until the next
+/// request.
+pub struct Activity {
+    last: Mutex<Instant>,
+    stopped: AtomicBool,
+    /// Lazy spawn: nothing has been started yet.
+    pending: AtomicBool,
+    /// Stopped on purpose with `shutdown_sidecar`.
+    shut_down: AtomicBool,
+    /// Why the last `wake_in_background` failed, for the next caller.
+    wake_error: Mutex<Option<String>>,
+}
+
+impl Activity {
+    pub fn new(lazy: bool) -> Self {
+        Self {
+            last: Mutex::new(Instant::now()),
+            stopped: AtomicBool::new(lazy),
+            pending: AtomicBool::new(lazy),
+            shut_down: AtomicBool::new(false),
+            wake_error: Mutex::new(None),
+        }
+    }
+
+    pub fn touch(&self) {
+        *self.last.lock_or_recover() = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last.lock_or_recover().elapsed()
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Stopped by the idle timer, as opposed to not started yet or shut
+    /// down on request.
+    pub fn is_idle_stopped(&self) -> bool {
+        self.is_stopped() && !self.pending.load(Ordering::SeqCst) && !self.is_shut_down()
+    }
+
+    /// Takes the error of a failed `wake_in_background`, if any.
+    pub fn take_wake_error(&self) -> Option<String> {
+        self.wake_error.lock_or_recover().take()
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.is_stopped() && self.shut_down.load(Ordering::SeqCst)
+    }
+
+    /// Records that the sidecar was stopped on request; the next one that
+    /// starts clears it.
+    pub fn shut_down(&self) {
+        self.shut_down.store(true, Ordering::SeqCst);
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    /// Records that a sidecar is up again, however it was started. Returns
+    /// whether this is the first one.
+    pub fn resumed(&self) -> bool {
+        *self.wake_error.lock_or_recover() = None;
+        self.stopped.store(false, Ordering::SeqCst);
+        self.shut_down.store(false, Ordering::SeqCst);
+        self.touch();
+        self.pending.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Stops the sidecar once it has been idle for `idle`. Runs for the lifetime
+/// of the app.
+pub fn watch(app_handle: &tauri::AppHandle, idle: Duration) {
+    let state = app_handle.state::<SidecarState>();
+    let interval = (idle / 4).clamp(MIN_CHECK_INTERVAL, MAX_CHECK_INTERVAL);
+
+    loop {
+        std::thread::sleep(interval);
+        if state.shutting_down.load(Ordering::SeqCst) {
+            return;
+        }
+        if state.activity.is_stopped() || state.activity.idle_for() < idle {
+            continue;
+        }
+
+        let mut sidecar = state.sidecar.lock_or_recover();
+        // A long request counts as use; so does one that arrived meanwhile.
+        if !state.in_flight.is_empty() {
+            state.activity.touch();
+            continue;
+        }
+        if state.activity.idle_for() < idle || !sidecar.has_child() {
+            continue;
+        }
+        log::info!("Sidecar idle for {}s, stopping it", idle.as_secs());
+        sidecar.kill();
+        state.activity.stopped.store(true, Ordering::SeqCst);
+        drop(sidecar);
+        let _ = app_handle.emit("sidecar-idle-stopped", ());
+    }
+}
+
+/// Starts the sidecar if the idle timer stopped it or lazy spawn hasn't
+/// started it yet; a no-op otherwise.
+pub async fn wake(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    if !app_handle.state::<SidecarState>().activity.is_stopped() {
+        return Ok(());
+    }
+    let handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || resume(&handle))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// `wake` without waiting for the sidecar to come up. Returns whether it
+/// was stopped, i.e. whether a start is now under way; if that start fails,
+/// `Activity::take_wake_error` has why.
+pub fn wake_in_background(app_handle: &tauri::AppHandle) -> bool {
+    if !app_handle.state::<SidecarState>().activity.is_stopped() {
+        return false;
+    }
+    let handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(err) = resume(&handle) {
+            let state = handle.state::<SidecarState>();
+            *state.activity.wake_error.lock_or_recover() = Some(err.to_string());
+        }
+    });
+    true
+}
+
+fn resume(app_handle: &tauri::AppHandle) -> Result<(), SidecarError> {
+    let state = app_handle.state::<SidecarState>();
+    let mut sidecar = state.sidecar.lock_or_recover();
+    // Concurrent callers queue on the lock; the first one respawns.
+    if !state.activity.is_stopped() {
+        return Ok(());
+    }
+    let port = sidecar.spawn(app_handle)?;
+    let pid = sidecar.snapshot().pid;
+    drop(sidecar);
+
+    let first = state.activity.resumed();
+    let ready = SidecarReady { port, pid };
+    if first {
+        log::info!("Sidecar started on first use, on port {port}");
+        let _ = app_handle.emit("sidecar-ready", ready);
+    } else {
+        log::info!("Sidecar resumed on port {port}");
+        let _ = app_handle.emit("sidecar-resumed", ready);
+    }
+    crate::ensure_monitor(app_handle);
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! Extra sidecars started on demand next to the default one, e.g. one per
+//! GPU for batch runs. Unlike the default sidecar they are not monitored or
+//! restarted; the caller owns their lifecycle.
+
+use std::collections::HashMap;
+use std::process::Child;
+use std::sync::Mutex;
+
+use crate::handshake::Handshake;
+use crate::process::kill_process_tree;
+use crate::sync::LockExt;
+
+/// Id the single-instance commands are addressed by.
+pub const DEFAULT_INSTANCE: &str = "default";
+
+const MAX_ID_LEN: usize = 32;
+
+struct NamedSidecar {
+    child: Child,
+    handshake: Handshake,
+}
+
+#[derive(Default)]
+pub struct Instances {
+    sidecars: Mutex<HashMap<String, NamedSidecar>>,
+}
+
+/// Ids end up in logs and error messages, so keep them to a safe alphabet.
+pub fn validate_id(id: &str) -> Result<(), String> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid sidecar id {id:?}: use up to {MAX_ID_LEN} letters, digits, '-' or '_'"
+        ))
+    }
+}
+
+impl Instances {
+    /// Whether `id` names a sidecar that is still running.
+    pub fn is_running(&self, id: &str) -> bool {
+        self.sidecars
+            .lock_or_recover()
+            .get_mut(id)
+            .is_some_and(|sidecar| matches!(sidecar.child.try_wait(), Ok(None)))
+    }
+
+    /// Stores a freshly spawned sidecar. Fails, killing `child`, if another
+    /// sidecar took `id` while this one was starting.
+    pub fn insert(&self, id: &str, mut child: Child, handshake: Handshake) -> Result<(), String> {
+        let mut sidecars = self.sidecars.lock_or_recover();
+        if let Some(existing) = sidecars.get_mut(id) {
+            if matches!(existing.child.try_wait(), Ok(None)) {
+                kill_process_tree(&mut child);
+                return Err(format!("Sidecar {id:?} is already running"));
+            }
+        }
+        sidecars.insert(id.to_string(), NamedSidecar { child, handshake });
+        Ok(())
+    }
+
+    pub fn port(&self, id: &str) -> Option<u16> {
+        self.sidecars
+            .lock_or_recover()
+            .get(id)
+            .map(|sidecar| sidecar.handshake.port)
+    }
+
+    /// Removes `id` so the caller can stop it.
+    pub fn take(&self, id: &str) -> Option<(Child, Handshake)> {
+        self.sidecars
+            .lock_or_recover()
+            .remove(id)
+            .map(|sidecar| (sidecar.child, sidecar.handshake))
+    }
+
+    pub fn take_all(&self) -> Vec<(Child, Handshake)> {
+        self.sidecars
+            .lock_or_recover()
+            .drain()
+            .map(|(_, sidecar)| (sidecar.child, sidecar.handshake))
+            .collect()
+    }
+}
@@ -0,0 +1,51 @@
+//! Checks the bundled sidecar against the SHA-256 the build ships next to
+//! it, so a binary truncated by antivirus fails with a clear error instead
+//! of misbehaving at runtime.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::errors::SidecarError;
+
+/// Compares `binary` with `<name>.sha256` beside it. Bundles without a
+/// checksum file (or with an unreadable one) are let through.
+pub fn verify(binary: &Path) -> Result<(), SidecarError> {
+    let checksum_path = binary.with_extension("sha256");
+    let expected = match std::fs::read_to_string(&checksum_path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            log::warn!("Cannot read {}: {err}", checksum_path.display());
+            return Ok(());
+        }
+    };
+    // Accept bare hex as well as `sha256sum` output (`<hex>  <file>`).
+    let Some(expected) = expected.split_whitespace().next() else {
+        log::warn!("{} is empty, skipping check", checksum_path.display());
+        return Ok(());
+    };
+
+    let actual = sha256_hex(binary).map_err(SidecarError::SpawnFailed)?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        log::error!(
+            "Sidecar checksum mismatch: expected {expected}, got {actual} for {}",
+            binary.display()
+        );
+        return Err(SidecarError::BinaryCorrupt(binary.to_path_buf()));
+    }
+    log::debug!("Sidecar checksum verified");
+    Ok(())
+}
+
+fn sha256_hex(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
@@ -0,0 +1,342 @@
+//! Choice of the program that runs the sidecar module in dev mode, the
+//! environment it starts with, and the app-independent part of launching
+//! it: spawning a command and waiting for its handshake. Those two take any
+//! `Command`, so tests drive them with a stub that prints a `PORT=` line.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::errors::SidecarError;
+use crate::handshake::Handshake;
+use crate::output::HandshakeResult;
+use crate::process::{kill_process_tree, use_own_process_group};
+
+/// Interpreters tried, in order, when `uv` isn't available.
+const PYTHON_CANDIDATES: &[&str] = if cfg!(windows) {
+    &["python"]
+} else {
+    &["python3", "python"]
+};
+
+/// Variables kept by `NOMEN_CLEAN_ENV`, besides the `NOMEN_*` ones. Windows
+/// processes also need their system directories; Python can't open a
+/// socket without `SYSTEMROOT`.
+const PASSTHROUGH_VARS: &[&str] = if cfg!(windows) {
+    &[
+        "PATH",
+        "USERPROFILE",
+        "SYSTEMROOT",
+        "TEMP",
+        "TMP",
+        "APPDATA",
+        "LOCALAPPDATA",
+    ]
+} else {
+    &["PATH", "HOME", "TMPDIR"]
+};
+
+/// Prefix of the app's own variables, which always pass through.
+const NOMEN_PREFIX: &str = "NOMEN_";
+
+/// Module run when `NOMEN_SIDECAR_MODULE` isn't set.
+const DEFAULT_MODULE: &str = "app";
+/// How often the handshake wait checks whether startup was cancelled.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Builds `<launcher> -m <module>`: `NOMEN_PYTHON_BIN` or `NOMEN_PYTHON` if
+/// set, otherwise `uv run`, falling back to a plain Python interpreter when
+/// `uv` isn't on PATH. The module is `app` unless `NOMEN_SIDECAR_MODULE`
+/// names another.
+pub fn dev_command() -> Result<Command, SidecarError> {
+    let module = module();
+    log::info!("Sidecar module: {module}");
+
+    if let Some(python) = config::python_bin() {
+        check_python_bin(&python)?;
+        log::info!(
+            "Launching sidecar with NOMEN_PYTHON_BIN ({})",
+            python.display()
+        );
+        let mut command = Command::new(python);
+        command.args(["-m", &module]);
+        return Ok(command);
+    }
+
+    if let Some(python) = config::python() {
+        log::info!("Launching sidecar with NOMEN_PYTHON ({python})");
+        let mut command = Command::new(python);
+        command.args(["-m", &module]);
+        return Ok(command);
+    }
+
+    if find_on_path("uv").is_some() {
+        log::info!("Launching sidecar with uv");
+        let mut command = Command::new("uv");
+        command.args(["run", "-m", &module]);
+        return Ok(command);
+    }
+
+    let python = PYTHON_CANDIDATES
+        .iter()
+        .copied()
+        .find(|candidate| find_on_path(candidate).is_some())
+        .unwrap_or("python");
+    log::warn!("uv not found on PATH, launching sidecar with {python}");
+    let mut command = Command::new(python);
+    command.args(["-m", &module]);
+    Ok(command)
+}
+
+/// Rejects a `NOMEN_PYTHON_BIN` that isn't an absolute path to an
+/// executable file.
+fn check_python_bin(path: &Path) -> Result<(), SidecarError> {
+    let reason = if !path.is_absolute() {
+        "is not an absolute path"
+    } else if !path.is_file() {
+        "does not exist"
+    } else if !crate::is_executable(path) {
+        "is not executable"
+    } else {
+        return Ok(());
+    };
+    Err(SidecarError::PythonBinInvalid {
+        path: path.to_path_buf(),
+        reason,
+    })
+}
+
+/// With `NOMEN_CLEAN_ENV`, starts `command` from an empty environment that
+/// only carries over `PASSTHROUGH_VARS` and the `NOMEN_*` variables, so
+/// strays like `PYTHONHOME` or `VIRTUAL_ENV` from the parent shell can't
+/// reach the sidecar. Call it before setting the sidecar's own variables,
+/// which `env_clear` would drop.
+pub fn apply_env_filter(command: &mut Command) {
+    if !config::clean_env() {
+        return;
+    }
+    command.env_clear();
+    let mut kept = Vec::new();
+    for (name, value) in env::vars_os() {
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if PASSTHROUGH_VARS
+            .iter()
+            .any(|var| var.eq_ignore_ascii_case(name))
+            || name.starts_with(NOMEN_PREFIX)
+        {
+            command.env(name, value);
+            kept.push(name.to_string());
+        }
+    }
+    kept.sort();
+    log::info!(
+        "Starting sidecar with a clean environment: {}",
+        kept.join(", ")
+    );
+}
+
+/// Spawns `command` with piped output as the leader of its own process group.
+pub fn spawn(command: &mut Command) -> Result<Child, SidecarError> {
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    use_own_process_group(command);
+    command
+        .spawn()
+        .map_err(|err| SidecarError::from_spawn(command.get_program(), err))
+}
+
+/// Hands the child's stdout to `read_stdout` on its own thread and waits
+/// until `deadline` for the handshake it sends back, or until `cancelled` is
+/// set. On failure the child is killed; `timeout` is only used to describe
+/// a timeout.
+pub fn await_handshake(
+    child: &mut Child,
+    read_stdout: impl FnOnce(ChildStdout, Sender<HandshakeResult>) + Send + 'static,
+    deadline: Instant,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Result<Handshake, SidecarError> {
+    let Some(stdout) = child.stdout.take() else {
+        kill_process_tree(child);
+        return Err(SidecarError::SpawnFailed(std::io::Error::other(
+            "Failed to capture sidecar stdout",
+        )));
+    };
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || read_stdout(stdout, tx));
+
+    let result = loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(remaining.min(CANCEL_POLL_INTERVAL)) {
+            Ok(result) => break result,
+            Err(RecvTimeoutError::Disconnected) => {
+                break Err(SidecarError::PortNeverReported(Vec::new()))
+            }
+            Err(RecvTimeoutError::Timeout) if cancelled.load(Ordering::SeqCst) => {
+                break Err(SidecarError::StartupCancelled)
+            }
+            Err(RecvTimeoutError::Timeout) if Instant::now() >= deadline => {
+                break Err(SidecarError::PortTimeout(timeout, Vec::new()))
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    };
+    if result.is_err() {
+        kill_process_tree(child);
+    }
+    result
+}
+
+/// The Python module the dev sidecar runs.
+pub fn module() -> String {
+    config::sidecar_module().unwrap_or_else(|| DEFAULT_MODULE.to_string())
+}
+
+/// Resolves `program` against PATH the way the OS would when spawning it.
+pub fn find_on_path(program: &str) -> Option<PathBuf> {
+    let file = format!("{program}{}", env::consts::EXE_SUFFIX);
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file))
+        .find(|path| path.is_file())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::headers::DefaultHeaders;
+    use crate::output;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+    const MAX_LINES: usize = 10;
+
+    /// A stand-in sidecar: a shell running `script`.
+    fn fake_sidecar(script: &str) -> Child {
+        spawn(Command::new("sh").args(["-c", script])).unwrap()
+    }
+
+    fn handshake(child: &mut Child, timeout: Duration) -> Result<Handshake, SidecarError> {
+        let read = |stdout, tx| output::read_lines(stdout, tx, MAX_LINES, |_| {});
+        let never = AtomicBool::new(false);
+        await_handshake(child, read, Instant::now() + timeout, timeout, &never)
+    }
+
+    #[test]
+    fn detects_the_announced_port() {
+        let mut child = fake_sidecar("echo starting; echo PORT=12345; exec sleep 30");
+
+        let handshake = handshake(&mut child, TIMEOUT).unwrap();
+
+        assert_eq!(handshake.port, 12345);
+        kill_process_tree(&mut child);
+    }
+
+    #[test]
+    fn rejects_a_malformed_port_line() {
+        let mut child = fake_sidecar("echo PORT=nope; exec sleep 30");
+
+        let err = handshake(&mut child, TIMEOUT).unwrap_err();
+
+        assert!(matches!(err, SidecarError::InvalidPort(line) if line == "PORT=nope"));
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn fails_fast_when_stdout_closes_without_a_port() {
+        let mut child = fake_sidecar("echo 'ImportError: no torch' >&2; exit 1");
+        let started = Instant::now();
+
+        let err = handshake(&mut child, TIMEOUT).unwrap_err();
+
+        assert!(matches!(err, SidecarError::PortNeverReported(_)));
+        assert!(started.elapsed() < TIMEOUT);
+    }
+
+    #[test]
+    fn gives_up_after_too_many_lines_without_a_port() {
+        let mut child = fake_sidecar("while :; do echo banner; done");
+        let started = Instant::now();
+
+        let err = handshake(&mut child, TIMEOUT).unwrap_err();
+
+        assert!(matches!(&err, SidecarError::HandshakeNotFound(lines) if lines.len() == MAX_LINES));
+        assert!(started.elapsed() < TIMEOUT);
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn stops_waiting_when_cancelled() {
+        let mut child = fake_sidecar("exec sleep 30");
+        let cancelled = std::sync::Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            flag.store(true, Ordering::SeqCst);
+        });
+        let started = Instant::now();
+        let read = |stdout, tx| output::read_lines(stdout, tx, MAX_LINES, |_| {});
+
+        let err =
+            await_handshake(&mut child, read, started + TIMEOUT, TIMEOUT, &cancelled).unwrap_err();
+
+        assert!(matches!(err, SidecarError::StartupCancelled));
+        assert!(started.elapsed() < TIMEOUT);
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn times_out_without_a_handshake() {
+        let mut child = fake_sidecar("exec sleep 30");
+
+        let err = handshake(&mut child, Duration::from_millis(300)).unwrap_err();
+
+        assert!(matches!(err, SidecarError::PortTimeout(..)));
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn stops_a_running_sidecar() {
+        let mut child = fake_sidecar("echo PORT=12345; exec sleep 30");
+        handshake(&mut child, TIMEOUT).unwrap();
+
+        crate::stop_sidecar(None, &DefaultHeaders::default(), &mut child);
+
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn rejects_unusable_python_bins() {
+        let reason = |path: &str| match check_python_bin(Path::new(path)) {
+            Err(SidecarError::PythonBinInvalid { reason, .. }) => Some(reason),
+            _ => None,
+        };
+
+        use std::os::unix::fs::PermissionsExt;
+        let plain = env::temp_dir().join(format!("nomen-python-bin-{}", std::process::id()));
+        std::fs::write(&plain, "").unwrap();
+        std::fs::set_permissions(&plain, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        assert_eq!(reason("python3"), Some("is not an absolute path"));
+        assert_eq!(reason("/nonexistent/python3"), Some("does not exist"));
+        assert_eq!(reason(plain.to_str().unwrap()), Some("is not executable"));
+        assert_eq!(reason("/bin/sh"), None);
+        let _ = std::fs::remove_file(plain);
+    }
+
+    #[test]
+    fn force_kills_without_a_grace_period() {
+        // Ignores SIGTERM, so only the force kill can stop it.
+        let mut child = fake_sidecar("trap '' TERM; echo PORT=12345; while :; do sleep 1; done");
+        handshake(&mut child, TIMEOUT).unwrap();
+
+        let graceful = crate::process::shutdown_process_tree(&mut child, Duration::ZERO);
+
+        assert!(!graceful);
+        assert!(child.try_wait().unwrap().is_some());
+    }
+}
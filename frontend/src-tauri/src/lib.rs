@@ -1,143 +1,1836 @@
+mod audio;
+mod auth;
+mod coalesce;
+mod config;
+mod device;
+mod errors;
+mod handle;
+mod handshake;
+mod headers;
+mod health;
+mod http;
+mod idle;
+mod instances;
+mod integrity;
+mod launcher;
+mod log_file;
+mod loopback;
+mod memory;
+mod orphan;
+mod output;
+mod process;
+mod proxy;
+mod resource_paths;
+mod scratch;
+mod self_test;
+mod spawn_info;
+mod startup;
+mod sync;
+mod system_info;
+mod timings;
+mod transcribe;
+mod usage;
+
+use std::collections::{HashSet, VecDeque};
 use std::env;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
-use std::sync::mpsc;
-use std::sync::Mutex;
-use std::time::Duration;
-use tauri::Manager;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Listener, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use config::RestartPolicy;
+use errors::{PortError, SidecarError};
+use handle::{SidecarHandle, Snapshot};
+use handshake::{Handshake, HandshakeStatus, LaunchMode};
+use headers::DefaultHeaders;
+use instances::{Instances, DEFAULT_INSTANCE};
+use log_file::RotatingLog;
+use output::{RecentLines, StartupStderr};
+use process::{exited_within, kill_process_tree, shutdown_process_tree};
+use spawn_info::SpawnInfo;
+use sync::LockExt;
+use timings::{StartupClock, StartupTimings};
+
+/// Resource subdirectory the bundle puts the sidecar in.
+const SIDECAR_RESOURCE_DIR: &str = "sidecar";
+const SIDECAR_BINARY: &str = if cfg!(target_os = "windows") {
+    "nomen-sidecar.exe"
+} else {
+    "nomen-sidecar"
+};
+
+/// Further ports tried, counting up, when `NOMEN_SIDECAR_FIXED_PORT` is taken.
+const FIXED_PORT_RETRIES: u16 = 3;
+/// How often the monitor thread checks whether the sidecar is still alive.
+const MONITOR_INTERVAL: Duration = Duration::from_millis(500);
+/// Auto-restarts allowed within `RESTART_WINDOW` before giving up.
+const MAX_RESTARTS: usize = 5;
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+/// Wait after the first failed restart; doubles with each further failure.
+const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// How often the heartbeat probes `/health` on a running sidecar.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Consecutive failed heartbeats before the sidecar counts as hung.
+const HEARTBEAT_FAILURES: u32 = 3;
+/// Whether a hung sidecar is restarted, not just reported.
+const RESTART_WHEN_UNRESPONSIVE: bool = true;
+/// How long to wait for the sidecar to exit after a `/shutdown` request.
+const HTTP_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+/// Output lines attached to a `sidecar-crashed` event.
+const CRASH_OUTPUT_LINES: usize = 20;
+/// How long a sidecar that died during startup gets to flush its stderr.
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Payload of the `sidecar-ready` event.
+#[derive(Clone, serde::Serialize)]
+struct SidecarReady {
+    port: u16,
+    pid: Option<u32>,
+}
+
+/// Payload of the `sidecar-crashed` event.
+#[derive(Clone, serde::Serialize)]
+struct SidecarCrashed {
+    code: Option<i32>,
+    /// Signal that killed the sidecar (Unix only), e.g. 9 for an OOM kill.
+    signal: Option<i32>,
+    /// The last lines the sidecar printed before it died.
+    output: Vec<String>,
+}
+
+/// Payload of the `sidecar-failed` event, sent when the monitor gives up.
+#[derive(Clone, serde::Serialize)]
+struct SidecarFailed {
+    attempts: u32,
+    error: String,
+}
+
+/// Lifecycle snapshot returned by `get_sidecar_status`.
+#[derive(serde::Serialize)]
+struct SidecarStatus {
+    running: bool,
+    /// Stopped by `NOMEN_IDLE_SHUTDOWN_SECS`; the next request restarts it.
+    idle_stopped: bool,
+    /// Stopped with `shutdown_sidecar`; likewise until the next request.
+    stopped: bool,
+    /// Set when `NOMEN_DISABLE_SIDECAR` keeps the sidecar from starting.
+    disabled: bool,
+    port: Option<u16>,
+    pid: Option<u32>,
+    restart_count: u32,
+    uptime_secs: u64,
+    version: Option<String>,
+    model: Option<String>,
+    /// Whether `warmup_sidecar` has finished loading the models.
+    warmed_up: bool,
+    /// How the running sidecar was started; `None` while it is down.
+    launch_mode: Option<LaunchMode>,
+    /// A spawn is in progress. The rest then describes no sidecar, since
+    /// the status is read without waiting for it.
+    starting: bool,
+}
+
+/// Counters for the diagnostics panel, returned by `get_sidecar_metrics`.
+#[derive(serde::Serialize)]
+struct SidecarMetrics {
+    /// Unix time (seconds) the current sidecar came up.
+    started_at: Option<u64>,
+    uptime_secs: u64,
+    restart_count: u32,
+    /// Exit code of the last sidecar that died; `None` if it was killed by
+    /// a signal or none has exited yet.
+    last_exit_code: Option<i32>,
+}
 
 struct SidecarState {
-    child: Mutex<Option<Child>>,
-    port: Mutex<u16>,
+    /// The default sidecar. Spawning, restarting and stopping hold this lock
+    /// throughout, so they queue up behind each other.
+    sidecar: Mutex<SidecarHandle>,
+    /// What `sidecar` last published; read this, not the lock, to find the
+    /// sidecar without waiting for a spawn or kill to finish.
+    published: handle::Published,
+    restart_count: AtomicU32,
+    /// Failed restart attempts in a row; reset by a successful spawn.
+    restart_failures: AtomicU32,
+    /// `NOMEN_RESTART_POLICY`, read at startup.
+    restart_policy: RestartPolicy,
+    /// `NOMEN_DISABLE_SIDECAR` was set at startup.
+    disabled: bool,
+    /// Set once shutdown starts, so the monitor doesn't treat the kill as a
+    /// crash and the close handler and the exit hooks only stop the sidecars once.
+    shutting_down: AtomicBool,
+    /// Set once `stop_sidecars` has finished; until then the app holds off
+    /// exiting.
+    sidecars_stopped: AtomicBool,
+    /// Whether a `monitor_sidecar` thread is currently watching the child.
+    monitoring: AtomicBool,
+    recent_output: RecentLines,
+    in_flight: proxy::InFlight,
+    queue: proxy::Queue,
+    activity: idle::Activity,
+    /// Sidecars started with `spawn_named_sidecar`.
+    instances: Instances,
+    /// How the most recent sidecar, of any instance, was launched.
+    spawn_info: Mutex<Option<SpawnInfo>>,
+    /// Set when the last spawn found less free RAM than `NOMEN_MIN_MEMORY_MB`.
+    memory_warning: Mutex<Option<memory::MemoryWarning>>,
+    /// Phases of the default sidecar's last successful startup.
+    startup_timings: Mutex<Option<StartupTimings>>,
+    /// The sidecar's `/system` answer, fetched once per session.
+    system_info: Mutex<Option<system_info::SystemInfo>>,
+    /// Labels of the open windows, which all share the sidecars; the last
+    /// one to close shuts them down.
+    windows: Mutex<HashSet<String>>,
+    /// Sent with every proxied call, readiness probe, heartbeat and
+    /// shutdown request; `sidecar` holds the same set.
+    headers: Arc<DefaultHeaders>,
+    /// Lets `cancel_sidecar_startup` abort spawns in progress.
+    startup: startup::Cancel,
+    /// Restarts asked for while one is running share its result.
+    restarts: coalesce::Coalesce<Result<u16, Arc<SidecarError>>>,
 }
 
-fn find_project_root() -> PathBuf {
-    let mut dir = env::current_dir().expect("Failed to get current dir");
-    loop {
+impl SidecarState {
+    fn snapshot(&self) -> Snapshot {
+        self.published.lock_or_recover().clone()
+    }
+
+    /// Marks the app as shutting down. Returns whether this is the first
+    /// call and there are sidecars to stop.
+    fn begin_shutdown(&self) -> bool {
+        !self.shutting_down.swap(true, Ordering::SeqCst) && !self.disabled
+    }
+}
+
+/// Cancels outstanding requests, stops the default sidecar (including one
+/// still starting) and every named instance, and tidies up after them.
+/// The sidecars are stopped side by side, so this takes as long as the
+/// slowest one rather than all of them. Callers go through `begin_shutdown`
+/// first, so this runs once.
+fn stop_sidecars(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<SidecarState>();
+    // First, so a spawn still starting gives up the lock `kill` needs.
+    state.startup.cancel();
+    proxy::cancel_all(&state);
+    let headers = &state.headers;
+    std::thread::scope(|scope| {
+        scope.spawn(|| state.sidecar.lock_or_recover().kill());
+        for (mut child, handshake) in state.instances.take_all() {
+            scope.spawn(move || stop_sidecar(Some(&handshake), headers, &mut child));
+        }
+    });
+    orphan::remove(app_handle);
+    if let Some(dir) = scratch::dir(app_handle) {
+        let _ = scratch::clear(&dir);
+    }
+}
+
+/// Locates the repo root (the directory holding `pyproject.toml`) for dev
+/// mode, either from `NOMEN_PROJECT_ROOT` or by walking up from the CWD.
+fn find_project_root() -> Result<PathBuf, SidecarError> {
+    // Guards against symlink loops and other odd filesystems.
+    const MAX_DEPTH: usize = 64;
+
+    if let Some(root) = config::project_root() {
+        return if root.join("pyproject.toml").exists() {
+            Ok(root)
+        } else {
+            Err(SidecarError::ProjectRootNotFound(root))
+        };
+    }
+
+    let start = env::current_dir().map_err(SidecarError::SpawnFailed)?;
+    let mut dir = start.clone();
+    for _ in 0..MAX_DEPTH {
         if dir.join("pyproject.toml").exists() {
-            return dir;
+            return Ok(dir);
         }
         if !dir.pop() {
-            panic!("Could not find project root (pyproject.toml)");
+            break;
         }
     }
+    Err(SidecarError::ProjectRootNotFound(start))
+}
+
+/// Starts the sidecar and waits until it's ready. Returns no child when
+/// `NOMEN_SIDECAR_PORT` points at a sidecar that was started by hand. With
+/// `NOMEN_SIDECAR_FIXED_PORT`, a taken port is retried on the next ones up.
+fn spawn_sidecar(
+    app_handle: &tauri::AppHandle,
+) -> Result<(Option<Child>, Handshake), SidecarError> {
+    if app_handle.state::<SidecarState>().disabled {
+        return Err(SidecarError::Disabled);
+    }
+    if let Some(value) = config::external_port() {
+        let port = value
+            .parse::<u16>()
+            .map_err(|_| SidecarError::InvalidPort(value.clone()))?;
+        let timeout = config::startup_timeout();
+        log::info!("Connecting to external sidecar on port {port}");
+        let token = config::auth_token();
+        let state = app_handle.state::<SidecarState>();
+        let _starting = state.startup.begin();
+        health::wait_until_ready(
+            port,
+            token.as_deref(),
+            &state.headers,
+            Instant::now() + timeout,
+            timeout,
+            state.startup.flag(),
+        )?;
+        let mut handshake = Handshake::from_port(port);
+        handshake.auth_token = token;
+        handshake.launch_mode = LaunchMode::External;
+        return Ok((None, handshake));
+    }
+    memory::check(app_handle);
+
+    spawn_instance(app_handle, DEFAULT_INSTANCE, None)
 }
 
-fn spawn_sidecar(app_handle: &tauri::AppHandle) -> (Child, u16) {
-    let mut child = if cfg!(debug_assertions) {
-        // Dev mode: spawn via uv run -m app
-        let project_root = find_project_root();
-        Command::new("uv")
-            .args(["run", "-m", "app"])
+/// Spawns sidecar `instance`, preferring `device` over the detected one.
+fn spawn_instance(
+    app_handle: &tauri::AppHandle,
+    instance: &str,
+    device: Option<&str>,
+) -> Result<(Option<Child>, Handshake), SidecarError> {
+    let state = app_handle.state::<SidecarState>();
+    let _starting = state.startup.begin();
+    let Some(first_port) = config::fixed_port() else {
+        return launch_sidecar(app_handle, instance, device, None);
+    };
+    let mut tried = Vec::new();
+    for offset in 0..=FIXED_PORT_RETRIES {
+        let Some(port) = first_port.checked_add(offset) else {
+            break;
+        };
+        match launch_sidecar(app_handle, instance, device, Some(port)) {
+            Err(SidecarError::PortUnavailable(_)) => {
+                log::warn!("Port {port} is already in use");
+                tried.push(port);
+            }
+            result => return result,
+        }
+    }
+    Err(SidecarError::PortUnavailable(tried))
+}
+
+/// Spawns one sidecar process and waits for it to become ready, asking it to
+/// listen on `fixed_port` if given.
+fn launch_sidecar(
+    app_handle: &tauri::AppHandle,
+    instance: &str,
+    device_override: Option<&str>,
+    fixed_port: Option<u16>,
+) -> Result<(Option<Child>, Handshake), SidecarError> {
+    let (mut command, token) = sidecar_command(app_handle, instance, device_override, fixed_port)?;
+    let cancelled = app_handle.state::<SidecarState>().inner().startup.flag();
+
+    // Not `{command:?}`, which would include the auth token from the env.
+    let spawn_info = SpawnInfo::capture(instance, &command);
+    log::debug!("Sidecar spawn: {spawn_info:?}");
+    *app_handle
+        .state::<SidecarState>()
+        .spawn_info
+        .lock_or_recover() = Some(spawn_info);
+
+    let timeout = config::startup_timeout();
+    let deadline = Instant::now() + timeout;
+    log::info!(
+        "Waiting up to {}s for sidecar {instance} to start ({} mode)",
+        timeout.as_secs(),
+        LaunchMode::spawned()
+    );
+
+    let mut clock = StartupClock::start();
+    let mut child = launcher::spawn(&mut command)?;
+    if instance == DEFAULT_INSTANCE {
+        orphan::record(app_handle, child.id());
+    }
+
+    let startup_stderr = Arc::new(StartupStderr::default());
+    let stderr_reader = child.stderr.take().map(|stderr| {
+        let log = sidecar_log_path(app_handle).and_then(|path| {
+            RotatingLog::open(&path)
+                .map_err(|err| log::warn!("Cannot open sidecar log {}: {err}", path.display()))
+                .ok()
+        });
+        let handle = app_handle.clone();
+        let startup = startup_stderr.clone();
+        std::thread::spawn(move || output::read_stderr(handle, stderr, log, startup))
+    });
+
+    let handle = app_handle.clone();
+    let first_output = clock.first_output();
+    let mut handshake = match launcher::await_handshake(
+        &mut child,
+        move |stdout, tx| output::read_stdout(handle, first_output.watch(stdout), tx),
+        deadline,
+        timeout,
+        cancelled,
+    ) {
+        Ok(handshake) => handshake,
+        Err(err) => return Err(with_startup_stderr(err, stderr_reader, &startup_stderr)),
+    };
+    clock.handshake_received();
+
+    if handshake.status == HandshakeStatus::PortInUse {
+        kill_process_tree(&mut child);
+        return Err(SidecarError::PortUnavailable(vec![handshake.port]));
+    }
+
+    handshake.auth_token = Some(token);
+
+    if let Err(err) = handshake.check_version() {
+        kill_process_tree(&mut child);
+        return Err(err);
+    }
+
+    if let Err(err) = health::wait_until_ready(
+        handshake.port,
+        handshake.auth_token.as_deref(),
+        &app_handle.state::<SidecarState>().headers,
+        deadline,
+        timeout,
+        cancelled,
+    ) {
+        kill_process_tree(&mut child);
+        return Err(with_startup_stderr(err, stderr_reader, &startup_stderr));
+    }
+
+    if let Err(err) = loopback::verify(handshake.port) {
+        kill_process_tree(&mut child);
+        return Err(err);
+    }
+    // Cancelled after the last wait: don't hand out a sidecar the user
+    // asked to abort.
+    if cancelled.load(Ordering::SeqCst) {
+        kill_process_tree(&mut child);
+        return Err(SidecarError::StartupCancelled);
+    }
+
+    startup_stderr.finish();
+    let timings = clock.ready();
+    if instance == DEFAULT_INSTANCE {
+        *app_handle
+            .state::<SidecarState>()
+            .startup_timings
+            .lock_or_recover() = Some(timings);
+    }
+
+    Ok((Some(child), handshake))
+}
+
+/// The command that starts sidecar `instance`, and the auth token it was
+/// handed.
+fn sidecar_command(
+    app_handle: &tauri::AppHandle,
+    instance: &str,
+    device_override: Option<&str>,
+    fixed_port: Option<u16>,
+) -> Result<(Command, String), SidecarError> {
+    let mut command = if cfg!(debug_assertions) {
+        // Dev mode: run the app module from the repo checkout
+        let project_root = find_project_root()?;
+        let mut command = launcher::dev_command()?;
+        launcher::apply_env_filter(&mut command);
+        command
             .env("PYTHONPATH", project_root.join("src"))
-            .current_dir(&project_root)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .expect("Failed to spawn sidecar — is `uv` installed?")
+            .current_dir(&project_root);
+        command
     } else {
         // Production mode: spawn bundled sidecar binary
         let resource_dir = app_handle
             .path()
             .resource_dir()
-            .expect("Failed to get resource directory");
-        let sidecar_path = resource_dir.join("sidecar").join("nomen-sidecar.exe");
+            .map_err(|err| SidecarError::ResourceDirUnavailable(err.to_string()))?;
+        let sidecar_path = find_sidecar_binary(&resource_dir)?;
+        if !is_executable(&sidecar_path) {
+            return Err(SidecarError::BinaryNotExecutable(sidecar_path));
+        }
+        integrity::verify(&sidecar_path)?;
 
-        if !sidecar_path.exists() {
-            panic!("Sidecar binary not found at {:?}", sidecar_path);
+        // Its model paths are relative to its own directory, not to wherever
+        // the launcher left the app's CWD.
+        let cwd = match config::sidecar_cwd() {
+            Some(dir) => {
+                let dir = resource_dir.join(dir);
+                if !dir.is_dir() {
+                    return Err(SidecarError::CwdNotFound(dir));
+                }
+                dir
+            }
+            None => sidecar_path
+                .parent()
+                .map_or_else(|| resource_dir.clone(), Path::to_path_buf),
+        };
+        log::info!("Sidecar {instance} working directory: {}", cwd.display());
+
+        let mut command = Command::new(&sidecar_path);
+        launcher::apply_env_filter(&mut command);
+        command.current_dir(cwd);
+        command
+    };
+
+    command.args(config::sidecar_args().map_err(SidecarError::InvalidArgs)?);
+    if let Some(path) = config::sidecar_config() {
+        if !path.is_file() {
+            return Err(SidecarError::ConfigNotFound(path));
         }
+        command.arg("--config").arg(path);
+    }
 
-        Command::new(&sidecar_path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .expect("Failed to spawn sidecar binary")
+    let token = auth::generate_token().map_err(|err| {
+        SidecarError::SpawnFailed(io::Error::other(format!(
+            "Failed to generate auth token: {err}"
+        )))
+    })?;
+    let detected = device::preferred();
+    let device = device_override.unwrap_or(&detected.device);
+    log::info!(
+        "Sidecar {instance} device: {device}{}",
+        if device_override.is_some() {
+            " (requested)"
+        } else if detected.overridden {
+            " (from NOMEN_DEVICE)"
+        } else {
+            ""
+        }
+    );
+    command
+        .env(device::DEVICE_ENV, device)
+        .env(auth::TOKEN_ENV, &token)
+        .env(loopback::BIND_HOST_ENV, loopback::BIND_HOST);
+    if let Some(dir) = scratch::dir(app_handle) {
+        command.env(scratch::SCRATCH_ENV, dir);
+    }
+    if let Some(port) = fixed_port {
+        command.env(config::FIXED_PORT_ENV, port.to_string());
+    }
+    Ok((command, token))
+}
+
+/// Attaches the stderr of a sidecar that never came up to errors that
+/// otherwise can't say why, once the killed sidecar's stderr reader has had
+/// a moment to drain what is still in the pipe.
+fn with_startup_stderr(
+    err: SidecarError,
+    stderr_reader: Option<std::thread::JoinHandle<()>>,
+    startup_stderr: &StartupStderr,
+) -> SidecarError {
+    if !matches!(
+        err,
+        SidecarError::PortTimeout(..) | SidecarError::PortNeverReported(_)
+    ) {
+        return err;
+    }
+    if let Some(reader) = stderr_reader {
+        let deadline = Instant::now() + STDERR_DRAIN_TIMEOUT;
+        while !reader.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+    let stderr = startup_stderr.finish();
+    match err {
+        SidecarError::PortTimeout(waited, _) => SidecarError::PortTimeout(waited, stderr),
+        _ => SidecarError::PortNeverReported(stderr),
+    }
+}
+
+/// Asks the sidecar to pause its background work when the window loses
+/// focus (including on minimize) and to resume when it regains it. Nothing
+/// is sent while the sidecar isn't running.
+fn pause_on_blur(app_handle: tauri::AppHandle, focused: bool) {
+    let path = if focused {
+        proxy::RESUME_PATH
+    } else {
+        proxy::PAUSE_PATH
     };
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<SidecarState>();
+        if let Err(err) = proxy::notify(&state, path).await {
+            log::debug!("Sidecar {path} not sent: {err}");
+        }
+    });
+}
 
-    let stdout = child.stdout.take().expect("Failed to capture sidecar stdout");
+/// Label carried by Tauri's `tauri://window-created` event.
+#[derive(serde::Deserialize)]
+struct WindowCreated {
+    label: String,
+}
 
-    let (tx, rx) = mpsc::channel();
+/// Keeps `SidecarState::windows` up to date with windows opened later. The
+/// ones from `tauri.conf.json` already exist when `setup` runs, before any
+/// creation event is heard.
+fn track_windows(app_handle: &tauri::AppHandle) {
+    let handle = app_handle.clone();
+    app_handle.listen_any("tauri://window-created", move |event| {
+        if let Ok(created) = serde_json::from_str::<WindowCreated>(event.payload()) {
+            let state = handle.state::<SidecarState>();
+            state.windows.lock_or_recover().insert(created.label);
+        }
+    });
+    let state = app_handle.state::<SidecarState>();
+    state
+        .windows
+        .lock_or_recover()
+        .extend(app_handle.webview_windows().into_keys());
+}
+
+/// Forgets a closed window; when it was the last one, cancels outstanding
+/// requests and stops every sidecar.
+fn window_closed(window: &tauri::Window) {
+    let state = window.state::<SidecarState>();
+    let mut windows = state.windows.lock_or_recover();
+    if windows.remove(window.label()) && !windows.is_empty() {
+        log::info!(
+            "Window {} closed, {} still open; keeping the sidecar",
+            window.label(),
+            windows.len()
+        );
+    }
+    if !windows.is_empty() {
+        return;
+    }
+    drop(windows);
+    // Several close events can fire; only the first shuts down.
+    if !state.begin_shutdown() {
+        return;
+    }
+    log::info!("Last window closing, shutting down sidecars");
+    stop_sidecars_then_exit(window.app_handle(), 0);
+}
+
+/// Runs `stop_sidecars` off the event loop, which `ExitRequested` keeps
+/// alive meanwhile, then exits with `code`.
+fn stop_sidecars_then_exit(app_handle: &tauri::AppHandle, code: i32) {
+    let handle = app_handle.clone();
     std::thread::spawn(move || {
-        let reader = BufReader::new(stdout);
-        let mut port_sent = false;
-        for line in reader.lines() {
-            let Ok(line) = line else { break };
-            if !port_sent {
-                if let Some(port_str) = line.strip_prefix("PORT=") {
-                    let port: u16 = port_str.parse().expect("Sidecar printed invalid port");
-                    let _ = tx.send(port);
-                    port_sent = true;
-                }
+        // Exit even if stopping them panicked.
+        let stopping = handle.clone();
+        let _ = std::thread::spawn(move || stop_sidecars(&stopping)).join();
+        let state = handle.state::<SidecarState>();
+        state.sidecars_stopped.store(true, Ordering::SeqCst);
+        handle.exit(code);
+    });
+}
+
+/// Rust target triple of the running build, naming the per-platform
+/// directory in universal bundles.
+fn target_triple() -> Option<&'static str> {
+    Some(match (env::consts::ARCH, env::consts::OS) {
+        ("aarch64", "macos") => "aarch64-apple-darwin",
+        ("x86_64", "macos") => "x86_64-apple-darwin",
+        ("x86_64", "windows") => "x86_64-pc-windows-msvc",
+        ("aarch64", "windows") => "aarch64-pc-windows-msvc",
+        ("x86_64", "linux") => "x86_64-unknown-linux-gnu",
+        ("aarch64", "linux") => "aarch64-unknown-linux-gnu",
+        _ => return None,
+    })
+}
+
+/// Finds the bundled binary: `sidecar/<target-triple>/` first, then the flat
+/// `sidecar/` layout of single-platform bundles. `NOMEN_SIDECAR_DIR` renames
+/// `sidecar/`.
+fn find_sidecar_binary(resource_dir: &Path) -> Result<PathBuf, SidecarError> {
+    let tried = sidecar_binary_candidates(resource_dir);
+    match tried.iter().find(|path| path.exists()) {
+        Some(path) => Ok(path.clone()),
+        None => Err(SidecarError::BinaryNotFound(tried)),
+    }
+}
+
+/// Where `find_sidecar_binary` looks, in order.
+fn sidecar_binary_candidates(resource_dir: &Path) -> Vec<PathBuf> {
+    let subdir = config::sidecar_dir().unwrap_or_else(|| SIDECAR_RESOURCE_DIR.to_string());
+    let sidecar_dir = resource_dir.join(subdir);
+    let mut candidates = Vec::new();
+    if let Some(triple) = target_triple() {
+        candidates.push(sidecar_dir.join(triple).join(SIDECAR_BINARY));
+    }
+    candidates.push(sidecar_dir.join(SIDECAR_BINARY));
+    candidates
+}
+
+/// Where the sidecar's stderr is persisted: `NOMEN_LOG_DIR` if it is
+/// usable, otherwise the app log dir if that resolves.
+fn sidecar_log_path(app_handle: &tauri::AppHandle) -> Option<PathBuf> {
+    if let Some(dir) = config::log_dir() {
+        let path = dir.join(log_file::SIDECAR_LOG_FILE);
+        match log_file::check_writable(&path) {
+            Ok(()) => return Some(path),
+            Err(err) => log::warn!(
+                "Can't write the sidecar log to NOMEN_LOG_DIR {} ({err}), using the app log directory",
+                dir.display()
+            ),
+        }
+    }
+    let dir = app_handle.path().app_log_dir().ok()?;
+    Some(dir.join(log_file::SIDECAR_LOG_FILE))
+}
+
+/// Spawns the sidecar the app starts with and, if that was us rather than
+/// an external one, starts supervising it.
+fn start_default_sidecar(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<SidecarState>();
+    // Counted from before the lock, so `get_sidecar_status` never waits on it.
+    let _starting = state.startup.begin();
+    let mut sidecar = state.sidecar.lock_or_recover();
+    let spawned = sidecar.spawn(app_handle);
+    let pid = sidecar.snapshot().pid;
+    drop(sidecar);
+    match spawned {
+        Ok(port) => {
+            let spawned = pid.is_some();
+            let ready = SidecarReady { port, pid };
+            let _ = app_handle.emit("sidecar-ready", ready);
+
+            // A sidecar we didn't spawn is the developer's to manage.
+            if spawned {
+                ensure_monitor(app_handle);
+                start_watchers(app_handle);
             }
-            // Keep draining stdout so the pipe buffer never fills.
-            // (PyTorch on Windows blocks if the pipe read-end closes.)
         }
+        Err(err) => report_startup_failure(app_handle, &err),
+    }
+}
+
+/// Starts a `monitor_sidecar` thread unless one is already running.
+fn ensure_monitor(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<SidecarState>();
+    if state.monitoring.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let handle = app_handle.clone();
+    std::thread::spawn(move || {
+        monitor_sidecar(&handle);
+        handle
+            .state::<SidecarState>()
+            .monitoring
+            .store(false, Ordering::SeqCst);
     });
+}
+
+/// Starts the heartbeat and, if configured, the idle timer, once per app.
+/// Both skip over periods with no sidecar, so they may start before the
+/// first spawn.
+fn start_watchers(app_handle: &tauri::AppHandle) {
+    static STARTED: Once = Once::new();
+    STARTED.call_once(|| spawn_watchers(app_handle));
+}
+
+fn spawn_watchers(app_handle: &tauri::AppHandle) {
+    let handle = app_handle.clone();
+    std::thread::spawn(move || heartbeat(&handle));
+    if let Some(idle) = config::idle_shutdown() {
+        let handle = app_handle.clone();
+        std::thread::spawn(move || idle::watch(&handle, idle));
+    }
+    if let Some(interval) = config::resource_usage_interval() {
+        let handle = app_handle.clone();
+        std::thread::spawn(move || usage::watch(&handle, interval));
+    }
+}
+
+/// Probes a live sidecar's `/health` so a hung process (one `try_wait` still
+/// sees as running) is noticed. Runs for the lifetime of the app.
+fn heartbeat(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<SidecarState>();
+    let mut failures = 0;
+
+    loop {
+        std::thread::sleep(HEARTBEAT_INTERVAL);
+        if state.shutting_down.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let snapshot = state.snapshot();
+        let alive = snapshot.running && snapshot.pid.is_some();
+        let Some(handshake) = snapshot.handshake.filter(|_| alive) else {
+            // Down or being restarted; the monitor handles that.
+            failures = 0;
+            continue;
+        };
+
+        let token = handshake.auth_token.as_deref();
+        if health::is_healthy(handshake.port, token, &state.headers, HEARTBEAT_TIMEOUT) {
+            failures = 0;
+            continue;
+        }
+        failures += 1;
+        if failures < HEARTBEAT_FAILURES {
+            continue;
+        }
+
+        log::warn!("Sidecar failed {failures} heartbeats in a row, treating it as hung");
+        failures = 0;
+        let _ = app_handle.emit("sidecar-unresponsive", ());
+        if RESTART_WHEN_UNRESPONSIVE && state.restart_policy != RestartPolicy::Off {
+            if let Err(err) = restart_coalesced(app_handle) {
+                log::error!("Failed to restart unresponsive sidecar: {err}");
+            }
+        }
+    }
+}
 
-    let port = rx
-        .recv_timeout(Duration::from_secs(30))
-        .expect("Sidecar did not report port within 30s");
+/// Watches the sidecar and, as `NOMEN_RESTART_POLICY` allows, respawns it
+/// if it exits while the app is still running, backing off after failed
+/// attempts. Gives up, emitting
+/// `sidecar-failed`, after `MAX_RESTARTS` attempts within `RESTART_WINDOW`
+/// or `NOMEN_MAX_RESTART_FAILURES` failures in a row.
+///
+/// Detection and respawn happen under the sidecar lock so they can't interleave
+/// with `restart_sidecar`.
+fn monitor_sidecar(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<SidecarState>();
+    let mut recent_restarts: VecDeque<Instant> = VecDeque::new();
+    let mut needs_restart = false;
 
-    (child, port)
+    loop {
+        std::thread::sleep(MONITOR_INTERVAL);
+        if state.shutting_down.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let mut sidecar = state.sidecar.lock_or_recover();
+        if state.activity.is_stopped() {
+            // Stopped on purpose (`shutdown_sidecar`, idle timer) or not
+            // started yet; whatever brings it back starts a fresh sidecar.
+            needs_restart = false;
+            continue;
+        }
+        if !needs_restart {
+            let Some(status) = sidecar.poll_exit() else {
+                continue;
+            };
+            log::warn!("Sidecar exited unexpectedly ({status})");
+            let crashed = SidecarCrashed {
+                code: status.code(),
+                signal: exit_signal(&status),
+                output: state.recent_output.tail(Some(CRASH_OUTPUT_LINES)),
+            };
+            let _ = app_handle.emit("sidecar-crashed", crashed);
+            let restart = match state.restart_policy {
+                RestartPolicy::Off => false,
+                RestartPolicy::OnCrash => !status.success(),
+                RestartPolicy::Always => true,
+            };
+            if !restart {
+                log::info!("Leaving the sidecar down, as NOMEN_RESTART_POLICY asks");
+                continue;
+            }
+            needs_restart = true;
+        } else if sidecar.has_child() {
+            // Brought back by `restart_sidecar` in the meantime.
+            needs_restart = false;
+            continue;
+        }
+
+        let now = Instant::now();
+        while recent_restarts
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > RESTART_WINDOW)
+        {
+            recent_restarts.pop_front();
+        }
+        if recent_restarts.len() >= MAX_RESTARTS {
+            let error = format!(
+                "Sidecar restarted {MAX_RESTARTS} times within {}s",
+                RESTART_WINDOW.as_secs()
+            );
+            log::error!("{error}, giving up");
+            let attempts = MAX_RESTARTS as u32;
+            let _ = app_handle.emit("sidecar-failed", SidecarFailed { attempts, error });
+            return;
+        }
+        recent_restarts.push_back(now);
+
+        match sidecar.spawn(app_handle) {
+            Ok(_) if !sidecar.has_child() => return,
+            Ok(port) => {
+                if state.shutting_down.load(Ordering::SeqCst) {
+                    sidecar.kill();
+                    return;
+                }
+                let count = state.restart_count.fetch_add(1, Ordering::SeqCst) + 1;
+                state.restart_failures.store(0, Ordering::SeqCst);
+                needs_restart = false;
+                log::info!("Sidecar restarted on port {port} (restart #{count})");
+                let _ = app_handle.emit("sidecar-restarted", port);
+            }
+            // `cancel_sidecar_startup` also stopped the sidecar for good.
+            Err(SidecarError::StartupCancelled) => needs_restart = false,
+            Err(err) => {
+                let failures = state.restart_failures.fetch_add(1, Ordering::SeqCst) + 1;
+                log::error!("Failed to restart sidecar (attempt {failures}): {err}");
+                if failures >= config::max_restart_failures() {
+                    log::error!("Sidecar failed to restart {failures} times in a row, giving up");
+                    let failed = SidecarFailed {
+                        attempts: failures,
+                        error: err.to_string(),
+                    };
+                    let _ = app_handle.emit("sidecar-failed", failed);
+                    return;
+                }
+                drop(sidecar);
+                let delay = restart_backoff(failures);
+                log::info!("Retrying sidecar restart in {}ms", delay.as_millis());
+                std::thread::sleep(delay);
+            }
+        }
+    }
+}
+
+/// Delay before the next restart after `failures` failed ones: exponential,
+/// capped, and jittered down by up to half so crash loops across machines
+/// don't line up.
+fn restart_backoff(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    let delay = RESTART_BACKOFF_BASE
+        .saturating_mul(1 << exponent)
+        .min(RESTART_BACKOFF_MAX);
+    let mut byte = [0u8; 1];
+    let _ = getrandom::getrandom(&mut byte);
+    delay.mul_f64(0.5 + f64::from(byte[0]) / 510.0)
 }
 
-fn kill_process_tree(child: &mut Child) {
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Stops `child`, first asking over HTTP so the sidecar can checkpoint work
+/// in progress, then by signal, and finally by force; with
+/// `NOMEN_KILL_GRACE_MS=0`, by force straight away. Returns whether it
+/// exited without the force kill.
+fn stop_sidecar(
+    handshake: Option<&Handshake>,
+    headers: &DefaultHeaders,
+    child: &mut Child,
+) -> bool {
     let pid = child.id();
-    let _ = Command::new("taskkill")
-        .args(["/F", "/T", "/PID", &pid.to_string()])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status();
+    let grace = config::kill_grace();
+    if grace.is_zero() {
+        log::info!("Sidecar (pid {pid}) has no grace period (NOMEN_KILL_GRACE_MS=0)");
+    } else if let Some(handshake) = handshake {
+        log::info!("Asking sidecar (pid {pid}) to shut down over HTTP");
+        if !health::request_shutdown(handshake.port, handshake.auth_token.as_deref(), headers) {
+            log::info!("Sidecar did not accept /shutdown, signalling it instead");
+        } else if exited_within(child, HTTP_SHUTDOWN_GRACE) {
+            log::info!("Sidecar (pid {pid}) stopped: exited after /shutdown");
+            return true;
+        } else {
+            log::info!(
+                "Sidecar did not exit within {}s of /shutdown, signalling it",
+                HTTP_SHUTDOWN_GRACE.as_secs()
+            );
+        }
+    }
+    if shutdown_process_tree(child, grace) {
+        log::info!("Sidecar (pid {pid}) stopped: exited after signal");
+        true
+    } else {
+        log::warn!("Sidecar (pid {pid}) stopped: force-killed");
+        false
+    }
+}
+
+/// Replaces the running sidecar with a fresh one.
+fn restart(app_handle: &tauri::AppHandle) -> Result<u16, SidecarError> {
+    let state = app_handle.state::<SidecarState>();
+    let _starting = state.startup.begin();
+    let mut sidecar = state.sidecar.lock_or_recover();
+    let port = sidecar.restart(app_handle)?;
+    let spawned = sidecar.has_child();
+    drop(sidecar);
+    state.restart_failures.store(0, Ordering::SeqCst);
+    state.activity.resumed();
+
+    let count = state.restart_count.fetch_add(1, Ordering::SeqCst) + 1;
+    log::info!("Sidecar restarted on port {port} (restart #{count})");
+    let _ = app_handle.emit("sidecar-restarted", port);
+    if spawned {
+        ensure_monitor(app_handle);
+        // Not running yet if the first spawn failed.
+        start_watchers(app_handle);
+    }
+    Ok(port)
+}
+
+/// `restart`, unless one is already under way: then the caller waits for
+/// that one and gets its result, so a burst of requests replaces the sidecar
+/// (and emits `sidecar-restarted`) once.
+fn restart_coalesced(app_handle: &tauri::AppHandle) -> Result<u16, Arc<SidecarError>> {
+    let state = app_handle.state::<SidecarState>();
+    state.restarts.run(|| restart(app_handle).map_err(Arc::new))
+}
+
+/// Tells the user the sidecar didn't start. A timeout may just be a slow
+/// machine, so it emits `sidecar-startup-timeout` and offers a retry, which
+/// goes through `restart_coalesced` like `restart_sidecar` does; anything
+/// else quits.
+fn report_startup_failure(app_handle: &tauri::AppHandle, err: &SidecarError) {
+    if matches!(err, SidecarError::StartupCancelled) {
+        log::info!("{err}");
+        return;
+    }
+    log::error!("{err}");
+    let handle = app_handle.clone();
+    let dialog = app_handle
+        .dialog()
+        .message(startup_error_message(err))
+        .title("Nomen Audio")
+        .kind(MessageDialogKind::Error);
+
+    let SidecarError::PortTimeout(timeout, _) = err else {
+        dialog.show(move |_| handle.exit(1));
+        return;
+    };
+    let _ = app_handle.emit("sidecar-startup-timeout", timeout.as_secs());
+    dialog
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Retry".to_string(),
+            "Quit".to_string(),
+        ))
+        .show(move |retry| {
+            if !retry {
+                handle.exit(1);
+                return;
+            }
+            std::thread::spawn(move || {
+                if let Err(err) = restart_coalesced(&handle) {
+                    report_startup_failure(&handle, &err);
+                }
+            });
+        });
 }
 
+/// Text for the startup-failure dialog, with a next step where there is one.
+fn startup_error_message(err: &SidecarError) -> String {
+    let hint = match err {
+        SidecarError::LauncherMissing(_) if cfg!(debug_assertions) => {
+            "\n\nInstall uv, or set NOMEN_PYTHON to a Python with the sidecar's dependencies."
+        }
+        SidecarError::LauncherMissing(_) => {
+            "\n\nThe installation looks incomplete; please reinstall."
+        }
+        SidecarError::LauncherNotExecutable(_) => {
+            "\n\nCheck that the file is executable by your user, or reinstall."
+        }
+        SidecarError::PythonBinInvalid { .. } => {
+            "\n\nPoint NOMEN_PYTHON_BIN at a Python interpreter, or unset it."
+        }
+        SidecarError::ResourceDirUnavailable(_) => {
+            "\n\nThe app can't find its engine files. If your system restricts \
+             where programs may read from, ask your administrator to allow Nomen Audio."
+        }
+        SidecarError::ConfigNotFound(_) => {
+            "\n\nFix the path in NOMEN_SIDECAR_CONFIG, or unset it to use the defaults."
+        }
+        SidecarError::CwdNotFound(_) => {
+            "\n\nFix the path in NOMEN_SIDECAR_CWD, or unset it to run from the engine's folder."
+        }
+        SidecarError::HandshakeNotFound(_) => {
+            "\n\nIf it is just chatty at startup, raise NOMEN_HANDSHAKE_MAX_LINES."
+        }
+        SidecarError::BinaryCorrupt(_) => {
+            "\n\nYour antivirus may have quarantined part of it; please reinstall."
+        }
+        _ => "",
+    };
+    format!("Nomen Audio could not start its audio engine.\n\n{err}{hint}")
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|meta| meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+/// Answers without touching the sidecar, so the frontend can tell a broken
+/// command bridge from a broken engine.
 #[tauri::command]
-fn get_sidecar_port(state: tauri::State<SidecarState>) -> Result<u16, String> {
-    let port = *state.port.lock().map_err(|e| e.to_string())?;
-    if port == 0 {
-        Err("Sidecar port not available".to_string())
-    } else {
+fn ping(app: tauri::AppHandle) -> String {
+    format!("pong {}", app.package_info().version)
+}
+
+/// Where the default sidecar listens, starting it first if it was stopped
+/// for idleness.
+async fn current_port(app: &tauri::AppHandle) -> Result<u16, String> {
+    idle::wake(app).await?;
+    app.state::<SidecarState>()
+        .snapshot()
+        .port()
+        .ok_or_else(|| "Sidecar port not available".to_string())
+}
+
+/// Base URL of the default sidecar (`http://127.0.0.1:<port>`), so the
+/// frontend needn't build it from the port.
+#[tauri::command]
+async fn get_sidecar_endpoint(app: tauri::AppHandle) -> Result<String, String> {
+    Ok(loopback::url(current_port(&app).await?))
+}
+
+/// Port of the default sidecar. Doesn't wait for a startup in progress,
+/// including one it begins for a stopped sidecar: that is
+/// `PortError::Starting`.
+#[tauri::command]
+async fn get_sidecar_port(app: tauri::AppHandle) -> Result<u16, PortError> {
+    let state = app.state::<SidecarState>();
+    if state.disabled {
+        return Err(PortError::Disabled);
+    }
+    if let Some(reason) = state.activity.take_wake_error() {
+        return Err(PortError::Crashed(reason));
+    }
+    if state.startup.is_starting() || idle::wake_in_background(&app) {
+        return Err(PortError::Starting);
+    }
+    let snapshot = state.snapshot();
+    match snapshot.port() {
+        Some(port) => Ok(port),
+        None => match (snapshot.last_error, snapshot.last_exit_code) {
+            (Some(reason), _) => Err(PortError::Crashed(reason)),
+            (None, Some(code)) => Err(PortError::Crashed(format!("exited with code {code}"))),
+            (None, None) => Err(PortError::NotStarted),
+        },
+    }
+}
+
+/// Bearer token the frontend must send with every sidecar request.
+#[tauri::command]
+fn get_sidecar_token(state: tauri::State<SidecarState>) -> Result<Option<String>, String> {
+    let snapshot = state.snapshot();
+    match snapshot.handshake {
+        Some(handshake) => Ok(handshake.auth_token),
+        None => Err("Sidecar is not running".to_string()),
+    }
+}
+
+/// Optional features the running sidecar announced, so the UI can hide the
+/// rest. Empty for sidecars that predate the field.
+#[tauri::command]
+fn get_sidecar_capabilities(state: tauri::State<SidecarState>) -> Result<Vec<String>, String> {
+    let snapshot = state.snapshot();
+    match snapshot.handshake {
+        Some(handshake) => Ok(handshake.capabilities),
+        None => Err("Sidecar is not running".to_string()),
+    }
+}
+
+/// OS pid of the current child, for attaching external tools. `None` when
+/// nothing is running or the sidecar was started outside the app.
+#[tauri::command]
+fn get_sidecar_pid(state: tauri::State<SidecarState>) -> Result<Option<u32>, String> {
+    let snapshot = state.snapshot();
+    Ok(snapshot.pid.filter(|_| snapshot.running))
+}
+
+/// What `call_sidecar` returns: the JSON response, and the id the request
+/// was sent and logged under.
+#[derive(serde::Serialize)]
+struct SidecarResponse {
+    request_id: String,
+    body: serde_json::Value,
+}
+
+/// Forwards a JSON request to the sidecar, attaching the auth token. Pass a
+/// `request_id` to be able to cancel the call with `cancel_sidecar_request`;
+/// a UUID is generated otherwise. Errors name the request id too.
+#[tauri::command]
+async fn call_sidecar(
+    app: tauri::AppHandle,
+    path: String,
+    method: String,
+    body: Option<serde_json::Value>,
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<SidecarResponse, String> {
+    let timeout = timeout_ms.map_or(proxy::REQUEST_TIMEOUT, Duration::from_millis);
+    let request_id = request_id.unwrap_or_else(proxy::new_request_id);
+    match proxy::call_queued(&app, &path, &method, body, timeout, &request_id).await {
+        Ok(body) => Ok(SidecarResponse { request_id, body }),
+        Err(err) => Err(format!("{err} (request {request_id})")),
+    }
+}
+
+/// Preloads the models so the first real request doesn't pay for it.
+/// Resolves once the sidecar reports them ready; returns whether they are.
+#[tauri::command]
+async fn warmup_sidecar(app: tauri::AppHandle) -> Result<bool, String> {
+    let request_id = proxy::new_request_id();
+    idle::wake(&app).await?;
+    let state = app.state::<SidecarState>();
+    let token = current_token(&state)?;
+    let response = proxy::call(
+        &app,
+        proxy::WARMUP_PATH,
+        "POST",
+        None,
+        proxy::WARMUP_TIMEOUT,
+        &request_id,
+    )
+    .await?;
+    if let Some(err) = response.get("error").and_then(serde_json::Value::as_str) {
+        log::warn!("Sidecar warm-up failed: {err}");
+    }
+    let ready = response
+        .get("ready")
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or(false);
+    state
+        .sidecar
+        .lock_or_recover()
+        .set_warmed_up(token.as_deref(), ready);
+    Ok(ready)
+}
+
+/// Token of the sidecar running now, so a result can be discarded if it was
+/// replaced while a request was in flight.
+fn current_token(state: &SidecarState) -> Result<Option<String>, String> {
+    Ok(state
+        .snapshot()
+        .handshake
+        .and_then(|handshake| handshake.auth_token))
+}
+
+/// Issues a GET or POST against the sidecar's `/model` route and records the
+/// model it reports as current.
+async fn call_model_route(
+    app: &tauri::AppHandle,
+    method: &str,
+    body: Option<serde_json::Value>,
+) -> Result<String, String> {
+    let request_id = proxy::new_request_id();
+    idle::wake(app).await?;
+    let state = app.state::<SidecarState>();
+    let token = current_token(&state)?;
+    let response = proxy::call(
+        app,
+        proxy::MODEL_PATH,
+        method,
+        body,
+        proxy::REQUEST_TIMEOUT,
+        &request_id,
+    )
+    .await?;
+    let model = response
+        .get("model")
+        .and_then(serde_json::Value::as_str)
+        .ok_or_else(|| "Sidecar did not report a model".to_string())?
+        .to_string();
+    state
+        .sidecar
+        .lock_or_recover()
+        .set_current_model(token.as_deref(), model.clone());
+    Ok(model)
+}
+
+/// Switches the sidecar to model `name`. It loads in the background; call
+/// `warmup_sidecar` to wait for it.
+#[tauri::command]
+async fn set_sidecar_model(app: tauri::AppHandle, name: String) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Model name must not be empty".to_string());
+    }
+    let model = call_model_route(&app, "POST", Some(serde_json::json!({ "name": name }))).await?;
+    log::info!("Sidecar model set to {model}");
+    Ok(model)
+}
+
+#[tauri::command]
+async fn get_sidecar_model(app: tauri::AppHandle) -> Result<String, String> {
+    call_model_route(&app, "GET", None).await
+}
+
+#[tauri::command]
+async fn list_sidecar_models(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    let request_id = proxy::new_request_id();
+    let response = proxy::call(
+        &app,
+        proxy::MODELS_PATH,
+        "GET",
+        None,
+        proxy::REQUEST_TIMEOUT,
+        &request_id,
+    )
+    .await?;
+    serde_json::from_value(response.get("models").cloned().unwrap_or_default())
+        .map_err(|e| format!("Invalid model list from sidecar: {e}"))
+}
+
+/// Proxies `/language`, returning the language the sidecar reports.
+async fn call_language_route(
+    app: &tauri::AppHandle,
+    method: &str,
+    body: Option<serde_json::Value>,
+) -> Result<String, String> {
+    let request_id = proxy::new_request_id();
+    let response = proxy::call(
+        app,
+        proxy::LANGUAGE_PATH,
+        method,
+        body,
+        proxy::REQUEST_TIMEOUT,
+        &request_id,
+    )
+    .await?;
+    response
+        .get("language")
+        .and_then(serde_json::Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "Sidecar did not report a language".to_string())
+}
+
+#[tauri::command]
+async fn get_sidecar_language(app: tauri::AppHandle) -> Result<String, String> {
+    call_language_route(&app, "GET", None).await
+}
+
+/// Switches the language captions are written in, without reloading the
+/// models. Codes the sidecar didn't announce at startup are turned away
+/// here; anything else it rejects comes back as its own error.
+#[tauri::command]
+async fn set_sidecar_language(app: tauri::AppHandle, code: String) -> Result<String, String> {
+    let code = code.trim();
+    if code.is_empty() {
+        return Err("Language code must not be empty".to_string());
+    }
+    idle::wake(&app).await?;
+    let languages = sidecar_languages(&app.state::<SidecarState>());
+    // Sidecars that predate the list check the code themselves.
+    if !languages.is_empty() && !languages.iter().any(|language| language == code) {
+        return Err(format!(
+            "Unsupported language {code:?}; the sidecar supports {}",
+            languages.join(", ")
+        ));
+    }
+    let language =
+        call_language_route(&app, "POST", Some(serde_json::json!({ "code": code }))).await?;
+    log::info!("Sidecar language set to {language}");
+    Ok(language)
+}
+
+/// Language codes the running sidecar announced; empty for sidecars that
+/// predate the field.
+#[tauri::command]
+fn list_sidecar_languages(state: tauri::State<SidecarState>) -> Result<Vec<String>, String> {
+    let snapshot = state.snapshot();
+    match snapshot.handshake {
+        Some(handshake) => Ok(handshake.languages),
+        None => Err("Sidecar is not running".to_string()),
+    }
+}
+
+fn sidecar_languages(state: &SidecarState) -> Vec<String> {
+    state
+        .snapshot()
+        .handshake
+        .map(|handshake| handshake.languages)
+        .unwrap_or_default()
+}
+
+/// Python, torch and host details from the sidecar, cached after the first
+/// call.
+#[tauri::command]
+async fn get_sidecar_system_info(app: tauri::AppHandle) -> Result<system_info::SystemInfo, String> {
+    system_info::get(&app).await
+}
+
+/// Streams the audio file at `path` to the sidecar to be described in
+/// words. Returns the request id at once; the text follows as a
+/// `sidecar-transcription` event with the same id.
+#[tauri::command]
+fn transcribe_file(
+    app: tauri::AppHandle,
+    path: String,
+    options: serde_json::Value,
+) -> Result<String, String> {
+    transcribe::start(&app, &path, options)
+}
+
+/// How many `call_sidecar` requests are waiting for a free slot.
+#[tauri::command]
+fn get_queue_depth(state: tauri::State<SidecarState>) -> usize {
+    state.queue.depth()
+}
+
+#[tauri::command]
+async fn cancel_sidecar_request(
+    state: tauri::State<'_, SidecarState>,
+    request_id: String,
+) -> Result<bool, String> {
+    proxy::cancel(&state, &request_id).await
+}
+
+#[tauri::command]
+fn get_detected_device() -> device::DetectedDevice {
+    device::preferred().clone()
+}
+
+/// Replaces the sidecar with a fresh one. Calls made while a restart is
+/// running wait for it and return its result instead of restarting again.
+#[tauri::command]
+async fn restart_sidecar(app: tauri::AppHandle) -> Result<u16, String> {
+    tauri::async_runtime::spawn_blocking(move || restart_coalesced(&app))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|err| err.to_string())
+}
+
+/// Stops the sidecar but keeps the app running, e.g. to review results
+/// without the engine holding memory. Nothing restarts it until the next
+/// request or `restart_sidecar`.
+#[tauri::command]
+async fn shutdown_sidecar(app: tauri::AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SidecarState>();
+        log::info!("Shutting down sidecar on request");
+        proxy::cancel_all(&state);
+        let mut sidecar = state.sidecar.lock_or_recover();
+        sidecar.kill();
+        state.activity.shut_down();
+        drop(sidecar);
+        let _ = app.emit("sidecar-stopped", ());
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Dev builds only, for leaked workers that outlive restarts: force-kills
+/// the tracked sidecars' process trees, then every other process whose
+/// command line looks like a sidecar. Returns the pids the scan killed; as
+/// after `shutdown_sidecar`, the next request starts a fresh sidecar.
+#[cfg(debug_assertions)]
+#[tauri::command]
+async fn force_cleanup_sidecars(app: tauri::AppHandle) -> Result<Vec<u32>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SidecarState>();
+        log::warn!("Force-killing every sidecar process");
+        proxy::cancel_all(&state);
+        let mut sidecar = state.sidecar.lock_or_recover();
+        sidecar.force_kill();
+        state.activity.shut_down();
+        drop(sidecar);
+        for (mut child, _) in state.instances.take_all() {
+            kill_process_tree(&mut child);
+        }
+        orphan::remove(&app);
+        let killed = orphan::kill_matching();
+        let _ = app.emit("sidecar-stopped", ());
+        Ok(killed)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Aborts a sidecar startup that is taking too long: the handshake and
+/// readiness waits give up within a fraction of a second and kill the
+/// half-started child. The sidecar then counts as stopped, as after
+/// `shutdown_sidecar`, until the next request or `restart_sidecar`. Returns
+/// whether a startup was in progress.
+#[tauri::command]
+fn cancel_sidecar_startup(app: tauri::AppHandle) -> Result<bool, String> {
+    let state = app.state::<SidecarState>();
+    if !state.startup.cancel() {
+        return Ok(false);
+    }
+    state.activity.shut_down();
+    log::info!("Cancelling sidecar startup on request");
+    let _ = app.emit("sidecar-stopped", ());
+    Ok(true)
+}
+
+/// Starts a throwaway sidecar, checks it comes up and answers, and shuts it
+/// down again, reporting each step. The managed sidecars are left alone.
+#[tauri::command]
+async fn sidecar_self_test(app: tauri::AppHandle) -> Result<self_test::SelfTestReport, String> {
+    if app.state::<SidecarState>().disabled {
+        return Err(SidecarError::Disabled.to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || self_test::run(&app))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Starts an extra sidecar under `id`, optionally on a specific device.
+#[tauri::command]
+async fn spawn_named_sidecar(
+    app: tauri::AppHandle,
+    id: String,
+    device: Option<String>,
+) -> Result<u16, String> {
+    if id == DEFAULT_INSTANCE {
+        return Err("The default sidecar is managed by the app; use restart_sidecar".to_string());
+    }
+    instances::validate_id(&id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SidecarState>();
+        if state.disabled {
+            return Err(SidecarError::Disabled.to_string());
+        }
+        if state.instances.is_running(&id) {
+            return Err(format!("Sidecar {id:?} is already running"));
+        }
+        let (child, handshake) =
+            spawn_instance(&app, &id, device.as_deref()).map_err(|e| e.to_string())?;
+        let Some(child) = child else {
+            return Err("Named sidecars cannot use NOMEN_SIDECAR_PORT".to_string());
+        };
+        let port = handshake.port;
+        state.instances.insert(&id, child, handshake)?;
+        log::info!("Sidecar {id} started on port {port}");
         Ok(port)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn get_named_sidecar_port(app: tauri::AppHandle, id: String) -> Result<u16, String> {
+    if id == DEFAULT_INSTANCE {
+        return get_sidecar_port(app).await.map_err(|e| e.to_string());
     }
+    let state = app.state::<SidecarState>();
+    state
+        .instances
+        .port(&id)
+        .ok_or_else(|| format!("No sidecar named {id:?}"))
+}
+
+#[tauri::command]
+async fn kill_named_sidecar(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    if id == DEFAULT_INSTANCE {
+        return Err("The default sidecar is managed by the app; use restart_sidecar".to_string());
+    }
+    let Some((mut child, handshake)) = app.state::<SidecarState>().instances.take(&id) else {
+        return Err(format!("No sidecar named {id:?}"));
+    };
+    let headers = Arc::clone(&app.state::<SidecarState>().headers);
+    tauri::async_runtime::spawn_blocking(move || {
+        stop_sidecar(Some(&handshake), &headers, &mut child);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Counts as use for the idle timer; the frontend calls it for requests
+/// that skip `call_sidecar`.
+#[tauri::command]
+fn mark_sidecar_active(state: tauri::State<SidecarState>) {
+    state.activity.touch();
+}
+
+#[tauri::command]
+fn get_log_level() -> String {
+    log::max_level().as_str().to_ascii_lowercase()
+}
+
+/// Changes the app log's verbosity until the next launch.
+#[tauri::command]
+fn set_log_level(level: String) -> Result<(), String> {
+    let filter = level.trim().parse::<log::LevelFilter>().map_err(|_| {
+        format!("Unknown log level {level:?}; use trace, debug, info, warn, error or off")
+    })?;
+    log::set_max_level(filter);
+    log::info!("Log level set to {filter}");
+    Ok(())
+}
+
+#[tauri::command]
+fn get_sidecar_log_path(app: tauri::AppHandle) -> Result<PathBuf, String> {
+    sidecar_log_path(&app).ok_or_else(|| "App log directory not available".to_string())
+}
+
+/// Phases of the last successful startup of the default sidecar, in
+/// milliseconds since spawn; `None` until one has come up.
+#[tauri::command]
+fn get_startup_timings(
+    state: tauri::State<SidecarState>,
+) -> Result<Option<StartupTimings>, String> {
+    Ok(state.startup_timings.lock_or_recover().clone())
+}
+
+/// The low-memory warning from the last spawn, for a UI that missed the
+/// `sidecar-insufficient-memory` event (it can fire before the page loads).
+#[tauri::command]
+fn get_memory_warning(
+    state: tauri::State<SidecarState>,
+) -> Result<Option<memory::MemoryWarning>, String> {
+    Ok(state.memory_warning.lock_or_recover().clone())
+}
+
+/// Empties the sidecar's scratch directory; returns how many entries were
+/// removed. Files a running job still holds open may be skipped.
+#[tauri::command]
+fn clear_sidecar_scratch(app: tauri::AppHandle) -> Result<usize, String> {
+    let dir = scratch::dir(&app).ok_or("Scratch directory is unavailable")?;
+    let removed = scratch::clear(&dir).map_err(|e| e.to_string())?;
+    log::info!("Cleared {removed} entries from {}", dir.display());
+    Ok(removed)
+}
+
+/// The settings file passed to the sidecar with `--config`, if any.
+#[tauri::command]
+fn get_sidecar_config_path() -> Option<PathBuf> {
+    config::sidecar_config()
+}
+
+/// The command line, working directory and `NOMEN_*` variables of the last
+/// spawn attempt, successful or not; `None` before the first.
+#[tauri::command]
+fn get_spawn_info(state: tauri::State<SidecarState>) -> Result<Option<SpawnInfo>, String> {
+    Ok(state.spawn_info.lock_or_recover().clone())
+}
+
+/// The resource dir, bundled-binary candidates and dev checkout the app
+/// resolves, each with whether it exists.
+#[tauri::command]
+fn get_resource_paths(app: tauri::AppHandle) -> resource_paths::ResourcePaths {
+    resource_paths::resolve(&app)
+}
+
+/// Adds a header to every request the app sends the sidecar, or changes its
+/// value, until `clear_sidecar_headers`.
+#[tauri::command]
+fn set_sidecar_header(
+    state: tauri::State<SidecarState>,
+    name: String,
+    value: String,
+) -> Result<(), String> {
+    state.headers.set(&name, &value)
+}
+
+/// Drops every header added by `set_sidecar_header` or `NOMEN_SIDECAR_HEADERS`.
+#[tauri::command]
+fn clear_sidecar_headers(state: tauri::State<SidecarState>) {
+    state.headers.clear();
+}
+
+#[tauri::command]
+fn get_recent_sidecar_logs(state: tauri::State<SidecarState>, lines: Option<usize>) -> Vec<String> {
+    state.recent_output.tail(lines)
+}
+
+#[tauri::command]
+fn get_sidecar_status(state: tauri::State<SidecarState>) -> Result<SidecarStatus, String> {
+    let starting = state.startup.is_starting();
+    let snapshot = state.snapshot();
+    let uptime_secs = snapshot
+        .started_at
+        .map_or(0, |started| started.elapsed().as_secs());
+    let (version, model) = snapshot
+        .handshake
+        .clone()
+        .map_or((None, None), |h| (h.version, h.model));
+    let model = snapshot.current_model.clone().or(model);
+
+    Ok(SidecarStatus {
+        running: snapshot.running,
+        idle_stopped: state.activity.is_idle_stopped(),
+        stopped: state.activity.is_shut_down(),
+        disabled: state.disabled,
+        port: snapshot.port(),
+        pid: snapshot.pid,
+        restart_count: state.restart_count.load(Ordering::SeqCst),
+        uptime_secs,
+        version,
+        model,
+        warmed_up: snapshot.warmed_up,
+        launch_mode: snapshot.handshake.as_ref().map(|h| h.launch_mode),
+        starting,
+    })
+}
+
+#[tauri::command]
+fn get_sidecar_metrics(state: tauri::State<SidecarState>) -> Result<SidecarMetrics, String> {
+    let snapshot = state.snapshot();
+    let uptime = snapshot.started_at.map(|started| started.elapsed());
+    Ok(SidecarMetrics {
+        started_at: uptime.and_then(|uptime| {
+            let started = SystemTime::now().checked_sub(uptime)?;
+            Some(started.duration_since(UNIX_EPOCH).ok()?.as_secs())
+        }),
+        uptime_secs: uptime.map_or(0, |uptime| uptime.as_secs()),
+        restart_count: state.restart_count.load(Ordering::SeqCst),
+        last_exit_code: snapshot.last_exit_code,
+    })
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let headers = Arc::new(DefaultHeaders::from_env());
+    let sidecar = SidecarHandle::new(Arc::clone(&headers));
+    let published = sidecar.published();
     tauri::Builder::default()
         .manage(SidecarState {
-            child: Mutex::new(None),
-            port: Mutex::new(0),
+            sidecar: Mutex::new(sidecar),
+            published,
+            restart_count: AtomicU32::new(0),
+            restart_failures: AtomicU32::new(0),
+            restart_policy: config::restart_policy(),
+            disabled: config::sidecar_disabled(),
+            shutting_down: AtomicBool::new(false),
+            sidecars_stopped: AtomicBool::new(false),
+            monitoring: AtomicBool::new(false),
+            recent_output: RecentLines::new(config::log_buffer_lines()),
+            in_flight: proxy::InFlight::default(),
+            queue: proxy::Queue::new(
+                config::max_concurrent_requests(),
+                config::max_queued_requests(),
+            ),
+            activity: idle::Activity::new(config::lazy_spawn()),
+            instances: Instances::default(),
+            spawn_info: Mutex::new(None),
+            startup_timings: Mutex::new(None),
+            memory_warning: Mutex::new(None),
+            system_info: Mutex::new(None),
+            windows: Mutex::new(HashSet::new()),
+            headers,
+            startup: startup::Cancel::default(),
+            restarts: coalesce::Coalesce::default(),
         })
-        .invoke_handler(tauri::generate_handler![get_sidecar_port])
+        .invoke_handler(tauri::generate_handler![
+            ping,
+            get_sidecar_port,
+            get_sidecar_endpoint,
+            get_sidecar_token,
+            get_sidecar_pid,
+            get_sidecar_capabilities,
+            call_sidecar,
+            cancel_sidecar_request,
+            get_queue_depth,
+            warmup_sidecar,
+            set_sidecar_model,
+            get_sidecar_model,
+            list_sidecar_models,
+            get_sidecar_status,
+            get_sidecar_metrics,
+            get_detected_device,
+            spawn_named_sidecar,
+            get_named_sidecar_port,
+            kill_named_sidecar,
+            restart_sidecar,
+            shutdown_sidecar,
+            mark_sidecar_active,
+            get_log_level,
+            set_log_level,
+            get_sidecar_log_path,
+            get_recent_sidecar_logs,
+            get_spawn_info,
+            get_sidecar_config_path,
+            get_startup_timings,
+            clear_sidecar_scratch,
+            get_memory_warning,
+            get_sidecar_system_info,
+            sidecar_self_test,
+            transcribe_file,
+            get_resource_paths,
+            set_sidecar_header,
+            clear_sidecar_headers,
+            cancel_sidecar_startup,
+            get_sidecar_language,
+            set_sidecar_language,
+            list_sidecar_languages,
+            #[cfg(debug_assertions)]
+            force_cleanup_sidecars
+        ])
         .setup(|app| {
-            let (child, port) = spawn_sidecar(app.handle());
-
-            let state = app.state::<SidecarState>();
-            *state.child.lock().unwrap() = Some(child);
-            *state.port.lock().unwrap() = port;
-
             app.handle().plugin(tauri_plugin_dialog::init())?;
             app.handle().plugin(tauri_plugin_http::init())?;
 
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+            let default_level = if cfg!(debug_assertions) {
+                log::LevelFilter::Debug
+            } else {
+                log::LevelFilter::Info
+            };
+            // The plugin lets everything through; `log`'s global max level does
+            // the filtering so `set_log_level` can change it at runtime.
+            app.handle().plugin(
+                tauri_plugin_log::Builder::default()
+                    .level(log::LevelFilter::Trace)
+                    .build(),
+            )?;
+            log::set_max_level(config::log_level().unwrap_or(default_level));
+
+            // Before anything spawns, so a leftover can't hold the port.
+            orphan::reap(app.handle());
+            track_windows(app.handle());
+
+            let state = app.state::<SidecarState>();
+            if state.disabled {
+                log::info!("NOMEN_DISABLE_SIDECAR is set, not starting the sidecar");
+                return Ok(());
+            }
+            if config::lazy_spawn() {
+                log::info!("NOMEN_LAZY_SPAWN is set, starting the sidecar on first use");
+                start_watchers(app.handle());
+                return Ok(());
             }
+            // Off the setup thread, so the window loads and can watch,
+            // or cancel, the startup.
+            let handle = app.handle().clone();
+            std::thread::spawn(move || start_default_sidecar(&handle));
 
             Ok(())
         })
-        .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
-                let state = window.state::<SidecarState>();
-                let mut guard = state.child.lock().unwrap();
-                if let Some(ref mut child) = *guard {
-                    kill_process_tree(child);
-                }
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::Focused(focused)
+                if config::pause_on_blur() && !window.state::<SidecarState>().disabled =>
+            {
+                pause_on_blur(window.app_handle().clone(), *focused);
             }
+            // A window destroyed from code gets no close request.
+            tauri::WindowEvent::CloseRequested { .. } | tauri::WindowEvent::Destroyed => {
+                window_closed(window);
+            }
+            _ => {}
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| match event {
+            // The event loop exits the process without dropping managed
+            // state, so exits that skip the last window closing (an exit
+            // call after a fatal error, say) are caught here. The exit waits
+            // until the sidecars are down.
+            tauri::RunEvent::ExitRequested { code, api, .. } => {
+                let state = app.state::<SidecarState>();
+                if state.disabled || state.sidecars_stopped.load(Ordering::SeqCst) {
+                    return;
+                }
+                if state.begin_shutdown() {
+                    log::info!("App exiting with sidecars still running, stopping them first");
+                    stop_sidecars_then_exit(app, code.unwrap_or(0));
+                }
+                api.prevent_exit();
+            }
+            // A restart can't be held off, so it stops them in place.
+            tauri::RunEvent::Exit if app.state::<SidecarState>().begin_shutdown() => {
+                log::info!("App exiting with sidecars still running, stopping them");
+                stop_sidecars(app);
+            }
+            _ => {}
+        });
 }
@@ -0,0 +1,89 @@
+//! Size-capped, rotating log file for the sidecar's stderr.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// File name of the sidecar log inside the app log directory.
+pub const SIDECAR_LOG_FILE: &str = "sidecar.log";
+
+/// Rotate once the current file would grow past this many bytes.
+const MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// Total files kept: `sidecar.log` plus `sidecar.log.1` .. `sidecar.log.{N-1}`.
+const KEEP_FILES: usize = 3;
+
+pub struct RotatingLog {
+    path: PathBuf,
+    /// Only `None` mid-rotation: Windows can't rename a file that's open.
+    file: Option<File>,
+    written: u64,
+}
+
+impl RotatingLog {
+    /// Opens `path` for appending, creating its parent directory if needed.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Some(file),
+            written,
+        })
+    }
+
+    /// Appends one line (which should already end in `\n`), rotating first
+    /// if it would push the file over the size cap.
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 > MAX_BYTES {
+            self.rotate()?;
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Err(io::Error::other("sidecar log is closed"));
+        };
+        file.write_all(line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        for index in (1..KEEP_FILES).rev() {
+            let from = if index == 1 {
+                self.path.clone()
+            } else {
+                numbered(&self.path, index - 1)
+            };
+            if from.exists() {
+                fs::rename(&from, numbered(&self.path, index))?;
+            }
+        }
+        self.file = Some(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&self.path)?,
+        );
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// Creates `path`'s parent directory if needed and checks the file can be
+/// appended to, without writing anything.
+pub fn check_writable(path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(())
+}
+
+fn numbered(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
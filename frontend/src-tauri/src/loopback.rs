@@ -0,0 +1,41 @@
+//! Guard against the sidecar listening on anything but loopback.
+
+use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
+use std::time::Duration;
+
+use crate::errors::SidecarError;
+
+/// Env var telling the sidecar which address to bind.
+pub const BIND_HOST_ENV: &str = "NOMEN_BIND_HOST";
+/// The only address the sidecar is allowed to listen on.
+pub const BIND_HOST: &str = "127.0.0.1";
+
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Base URL of a sidecar listening on `port`.
+pub fn url(port: u16) -> String {
+    format!("http://{BIND_HOST}:{port}")
+}
+
+/// Fails if `port` also accepts connections on this machine's LAN address.
+/// Machines without a routable interface have nothing to expose and pass.
+pub fn verify(port: u16) -> Result<(), SidecarError> {
+    let Some(ip) = lan_address() else {
+        log::debug!("No LAN address found, skipping loopback check");
+        return Ok(());
+    };
+    let addr = SocketAddr::new(ip, port);
+    if TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok() {
+        return Err(SidecarError::ExposedOnNetwork(addr));
+    }
+    Ok(())
+}
+
+/// The address of the interface outbound traffic would use. Connecting a UDP
+/// socket only picks a route; nothing is sent.
+fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_loopback() && !ip.is_unspecified()).then_some(ip)
+}
@@ -0,0 +1,102 @@
+//! Pre-spawn check for available RAM. Loading the models on a machine
+//! without enough free memory gets the sidecar OOM-killed straight away;
+//! warning first turns that into something the user can act on. The check
+//! only warns; the sidecar is started regardless.
+
+use tauri::{Emitter, Manager};
+
+use crate::sync::LockExt;
+use crate::{config, SidecarState};
+
+/// Payload of the `sidecar-insufficient-memory` event, also returned by
+/// `get_memory_warning`.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct MemoryWarning {
+    pub available_mb: u64,
+    pub required_mb: u64,
+}
+
+/// Compares available RAM against `NOMEN_MIN_MEMORY_MB`, emitting
+/// `sidecar-insufficient-memory` and recording the warning when it falls
+/// short. Platforms where the figure can't be read pass.
+pub fn check(app_handle: &tauri::AppHandle) {
+    let required_mb = config::min_memory_mb();
+    let warning = match available_mb() {
+        Some(available_mb) if required_mb > 0 && available_mb < required_mb => {
+            log::warn!(
+                "Only {available_mb} MB of memory available, the sidecar wants {required_mb} MB"
+            );
+            Some(MemoryWarning {
+                available_mb,
+                required_mb,
+            })
+        }
+        Some(available_mb) => {
+            log::debug!("{available_mb} MB of memory available");
+            None
+        }
+        None => None,
+    };
+    let state = app_handle.state::<SidecarState>();
+    *state.memory_warning.lock_or_recover() = warning.clone();
+    if let Some(warning) = warning {
+        let _ = app_handle.emit("sidecar-insufficient-memory", warning);
+    }
+}
+
+const MB: u64 = 1024 * 1024;
+
+/// Memory available to new processes without swapping, in MB.
+#[cfg(target_os = "linux")]
+fn available_mb() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find(|line| line.starts_with("MemAvailable:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024 / MB)
+}
+
+/// Free, inactive and speculative pages from `vm_stat`; macOS reclaims the
+/// latter two on demand.
+#[cfg(target_os = "macos")]
+fn available_mb() -> Option<u64> {
+    let output = std::process::Command::new("vm_stat").output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let page_size: u64 = text
+        .lines()
+        .next()?
+        .split("page size of ")
+        .nth(1)?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    let pages = |label: &str| -> Option<u64> {
+        let line = text.lines().find(|line| line.starts_with(label))?;
+        line.rsplit(':')
+            .next()?
+            .trim()
+            .trim_end_matches('.')
+            .parse()
+            .ok()
+    };
+    let free = pages("Pages free")? + pages("Pages inactive")? + pages("Pages speculative")?;
+    Some(free * page_size / MB)
+}
+
+#[cfg(windows)]
+fn available_mb() -> Option<u64> {
+    use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+    let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+    status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+    if unsafe { GlobalMemoryStatusEx(&mut status) } == 0 {
+        return None;
+    }
+    Some(status.ullAvailPhys / MB)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn available_mb() -> Option<u64> {
+    None
+}
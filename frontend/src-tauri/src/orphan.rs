@@ -0,0 +1,341 @@
+//! Cleanup after an app crash. The default sidecar's pid is written to a
+//! file in the app data directory when it spawns, next to the pid of the
+//! app that owns it, and removed on a clean close. An entry whose owner is
+//! gone at the next launch names an orphan, which is killed before a new
+//! sidecar starts so it can't keep holding its port. Entries of another
+//! instance that is still running are left alone.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tauri::Manager;
+
+use crate::launcher;
+
+/// File name of the pidfile inside the app data directory.
+const PIDFILE: &str = "sidecar.pid";
+
+/// Name shared by the bundled binary and its platform variants.
+const BINARY_MARKER: &str = "nomen-sidecar";
+
+fn pidfile(app_handle: &tauri::AppHandle) -> Option<PathBuf> {
+    Some(app_handle.path().app_data_dir().ok()?.join(PIDFILE))
+}
+
+/// A pidfile line: `<sidecar pid> <app pid>`. Files written before owners
+/// were recorded hold a bare sidecar pid.
+struct Entry {
+    sidecar: u32,
+    owner: Option<u32>,
+}
+
+fn read_entries(path: &Path) -> Vec<Entry> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let sidecar = fields.next()?.parse().ok()?;
+            let owner = fields.next().and_then(|owner| owner.parse().ok());
+            Some(Entry { sidecar, owner })
+        })
+        .collect()
+}
+
+/// Writes `entries`, or removes the file when there are none.
+fn write_entries(path: &Path, entries: &[Entry]) -> io::Result<()> {
+    if entries.is_empty() {
+        return match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        };
+    }
+    let contents: String = entries
+        .iter()
+        .map(|entry| match entry.owner {
+            Some(owner) => format!("{} {owner}\n", entry.sidecar),
+            None => format!("{}\n", entry.sidecar),
+        })
+        .collect();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
+
+/// Replaces this app's entry with `sidecar`, or drops it for `None`.
+fn update_own_entry(app_handle: &tauri::AppHandle, sidecar: Option<u32>) {
+    let Some(path) = pidfile(app_handle) else {
+        return;
+    };
+    let own = std::process::id();
+    let mut entries = read_entries(&path);
+    entries.retain(|entry| entry.owner != Some(own));
+    entries.extend(sidecar.map(|sidecar| Entry {
+        sidecar,
+        owner: Some(own),
+    }));
+    if let Err(err) = write_entries(&path, &entries) {
+        log::warn!("Cannot update sidecar pidfile {}: {err}", path.display());
+    }
+}
+
+/// Remembers `pid` as this app's running sidecar.
+pub fn record(app_handle: &tauri::AppHandle, pid: u32) {
+    update_own_entry(app_handle, Some(pid));
+}
+
+/// Forgets this app's sidecar on a clean close.
+pub fn remove(app_handle: &tauri::AppHandle) {
+    update_own_entry(app_handle, None);
+}
+
+/// Kills the sidecars crashed runs left behind, if they are still alive and
+/// still look like sidecars (the pid may have been reused). Sidecars whose
+/// app is still running belong to another instance and are kept.
+pub fn reap(app_handle: &tauri::AppHandle) {
+    let Some(path) = pidfile(app_handle) else {
+        return;
+    };
+    let entries = read_entries(&path);
+    if entries.is_empty() {
+        return;
+    }
+    let own = std::process::id();
+    let mut kept = Vec::new();
+    for entry in entries {
+        match entry.owner {
+            Some(owner) if owner != own && is_running_app(owner) => {
+                log::debug!(
+                    "Sidecar pid {} belongs to running instance {owner}, keeping it",
+                    entry.sidecar
+                );
+                kept.push(entry);
+            }
+            _ => reap_orphan(entry.sidecar),
+        }
+    }
+    if let Err(err) = write_entries(&path, &kept) {
+        log::warn!("Cannot update sidecar pidfile {}: {err}", path.display());
+    }
+}
+
+fn reap_orphan(pid: u32) {
+    let Some(command_line) = command_line(pid) else {
+        log::debug!("Sidecar pid {pid} from an earlier run is gone");
+        return;
+    };
+    if !is_sidecar(&command_line) {
+        log::debug!("Pid {pid} from an earlier run is no longer a sidecar: {command_line}");
+        return;
+    }
+    log::warn!("Killing sidecar (pid {pid}) orphaned by an earlier run");
+    kill(pid);
+}
+
+/// Whether `pid` is alive and runs this app's executable (the pid may have
+/// been reused since it was recorded).
+fn is_running_app(pid: u32) -> bool {
+    if command_line(pid).is_none() {
+        return false;
+    }
+    let (Some(theirs), Ok(ours)) = (executable(pid), std::env::current_exe()) else {
+        // Can't tell; keeping a live sidecar beats killing another app's.
+        return true;
+    };
+    let resolve = |path: PathBuf| fs::canonicalize(&path).unwrap_or(path);
+    resolve(theirs) == resolve(ours)
+}
+
+/// The path of the executable a live process runs.
+#[cfg(target_os = "linux")]
+fn executable(pid: u32) -> Option<PathBuf> {
+    let path = fs::read_link(format!("/proc/{pid}/exe")).ok()?;
+    // An executable replaced since it started (an update) reads as deleted.
+    match path.to_str()?.strip_suffix(" (deleted)") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Some(path),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn executable(pid: u32) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut buffer = vec![0u8; libc::PROC_PIDPATHINFO_MAXSIZE as usize];
+    let len = unsafe {
+        libc::proc_pidpath(
+            pid as libc::c_int,
+            buffer.as_mut_ptr().cast(),
+            buffer.len() as u32,
+        )
+    };
+    let len = usize::try_from(len).ok().filter(|len| *len > 0)?;
+    Some(PathBuf::from(std::ffi::OsStr::from_bytes(&buffer[..len])))
+}
+
+/// No portable way to ask; [`is_running_app`] then keeps the entry.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "macos"))))]
+fn executable(_pid: u32) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(windows)]
+fn executable(pid: u32) -> Option<PathBuf> {
+    use std::os::windows::ffi::OsStringExt;
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if process.is_null() {
+        return None;
+    }
+    let mut buffer = vec![0u16; 32_768];
+    let mut len = buffer.len() as u32;
+    let ok = unsafe {
+        QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut len) != 0
+    };
+    unsafe { CloseHandle(process) };
+    if !ok {
+        return None;
+    }
+    Some(PathBuf::from(std::ffi::OsString::from_wide(
+        &buffer[..len as usize],
+    )))
+}
+
+/// Whether `command_line` runs the bundled binary or the dev launcher's
+/// `-m <module>`.
+fn is_sidecar(command_line: &str) -> bool {
+    let module = launcher::module();
+    let args: Vec<&str> = command_line.split_whitespace().collect();
+    command_line.contains(BINARY_MARKER) || args.windows(2).any(|pair| pair == ["-m", &module])
+}
+
+/// Kills every other process that looks like a sidecar, whether the app
+/// tracks it or not, and returns their pids. For digging out of leaked
+/// workers in dev; release builds never scan.
+#[cfg(debug_assertions)]
+pub fn kill_matching() -> Vec<u32> {
+    let own = std::process::id();
+    let mut killed = Vec::new();
+    for (pid, command_line) in processes() {
+        if pid == own || !is_sidecar(&command_line) {
+            continue;
+        }
+        log::warn!("Force-killing sidecar process {pid}: {command_line}");
+        kill(pid);
+        killed.push(pid);
+    }
+    killed
+}
+
+/// Every live process with its command line.
+#[cfg(all(debug_assertions, target_os = "linux"))]
+fn processes() -> Vec<(u32, String)> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| Some((pid, command_line(pid)?)))
+        .collect()
+}
+
+#[cfg(all(debug_assertions, unix, not(target_os = "linux")))]
+fn processes() -> Vec<(u32, String)> {
+    let Ok(output) = std::process::Command::new("ps")
+        .args(["-axo", "pid=,command="])
+        .output()
+    else {
+        return Vec::new();
+    };
+    parse_pid_lines(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(all(debug_assertions, windows))]
+fn processes() -> Vec<(u32, String)> {
+    let query =
+        "Get-CimInstance Win32_Process | ForEach-Object { \"$($_.ProcessId) $($_.CommandLine)\" }";
+    let Ok(output) = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", query])
+        .output()
+    else {
+        return Vec::new();
+    };
+    parse_pid_lines(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses `<pid> <command line>` lines, skipping processes without one.
+#[cfg(all(debug_assertions, not(target_os = "linux")))]
+fn parse_pid_lines(text: &str) -> Vec<(u32, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let (pid, command_line) = line.trim().split_once(char::is_whitespace)?;
+            let command_line = command_line.trim();
+            if command_line.is_empty() {
+                return None;
+            }
+            Some((pid.parse().ok()?, command_line.to_string()))
+        })
+        .collect()
+}
+
+/// The command line of a live process, arguments joined by spaces.
+#[cfg(target_os = "linux")]
+fn command_line(pid: u32) -> Option<String> {
+    let raw = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    let args: Vec<String> = raw
+        .split(|byte| *byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    (!args.is_empty()).then(|| args.join(" "))
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn command_line(pid: u32) -> Option<String> {
+    let output = std::process::Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "command="])
+        .output()
+        .ok()?;
+    let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !line.is_empty()).then_some(line)
+}
+
+#[cfg(windows)]
+fn command_line(pid: u32) -> Option<String> {
+    let query = format!("(Get-CimInstance Win32_Process -Filter 'ProcessId={pid}').CommandLine");
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &query])
+        .output()
+        .ok()?;
+    let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!line.is_empty()).then_some(line)
+}
+
+/// The sidecar led its own process group, so its workers go with it.
+#[cfg(unix)]
+fn kill(pid: u32) {
+    let pid = pid as libc::pid_t;
+    unsafe {
+        libc::kill(-pid, libc::SIGKILL);
+        libc::kill(pid, libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill(pid: u32) {
+    use std::process::{Command, Stdio};
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
@@ -0,0 +1,345 @@
+//! Consumption of the sidecar's stdout: port handshake and log forwarding.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::ChildStderr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use tauri::{Emitter, Manager};
+
+use crate::errors::SidecarError;
+use crate::handshake::{self, Handshake};
+use crate::log_file::RotatingLog;
+use crate::sync::LockExt;
+use crate::{config, SidecarState};
+
+/// The parsed handshake; `InvalidPort` with the offending line if it was
+/// invalid, `HandshakeNotFound` if too many lines came first, or
+/// `PortNeverReported` if stdout closed before one arrived.
+pub type HandshakeResult = Result<Handshake, SidecarError>;
+
+/// Payload of the `sidecar-log` event.
+#[derive(Clone, serde::Serialize)]
+pub struct SidecarLog {
+    pub line: String,
+    pub level: Option<&'static str>,
+}
+
+/// Payload of the `sidecar-starting` event.
+#[derive(Clone, serde::Serialize)]
+pub struct SidecarStarting {
+    pub line: String,
+}
+
+/// Payload of the `sidecar-progress` event.
+#[derive(Clone, serde::Serialize)]
+pub struct SidecarProgress {
+    pub percent: f32,
+    pub stage: Option<String>,
+}
+
+const PROGRESS_PREFIX: &str = "PROGRESS=";
+const STAGE_PREFIX: &str = "STAGE=";
+
+/// Parses `PROGRESS=<pct>` (optionally followed by ` STAGE=<name>`) or a bare
+/// `STAGE=<name>`, which starts a stage at 0%. `stage` carries the current
+/// stage between lines.
+fn parse_progress(line: &str, stage: &mut Option<String>) -> Option<SidecarProgress> {
+    let line = line.trim();
+    if let Some(name) = line.strip_prefix(STAGE_PREFIX) {
+        *stage = Some(name.trim().to_string());
+        return Some(SidecarProgress {
+            percent: 0.0,
+            stage: stage.clone(),
+        });
+    }
+    let rest = line.strip_prefix(PROGRESS_PREFIX)?;
+    let (percent, inline_stage) = match rest.split_once(char::is_whitespace) {
+        Some((percent, tail)) => (percent, tail.trim().strip_prefix(STAGE_PREFIX)),
+        None => (rest, None),
+    };
+    let percent = percent.parse::<f32>().ok()?.clamp(0.0, 100.0);
+    if let Some(name) = inline_stage {
+        *stage = Some(name.trim().to_string());
+    }
+    Some(SidecarProgress {
+        percent,
+        stage: stage.clone(),
+    })
+}
+
+/// Bounded buffer of the most recent sidecar output lines.
+pub struct RecentLines {
+    lines: Mutex<VecDeque<String>>,
+    capacity: usize,
+}
+
+impl RecentLines {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock_or_recover();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// The last `count` lines (all of them if `None`), oldest first.
+    pub fn tail(&self, count: Option<usize>) -> Vec<String> {
+        let lines = self.lines.lock_or_recover();
+        let skip = count.map_or(0, |count| lines.len().saturating_sub(count));
+        lines.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Stderr lines kept for a startup error; enough for a traceback's tail.
+const STARTUP_STDERR_LINES: usize = 20;
+
+/// The first stderr lines of a sidecar that is still starting, so a failed
+/// startup can say why. Capture stops once `finish` is called.
+#[derive(Default)]
+pub struct StartupStderr {
+    lines: Mutex<Vec<String>>,
+    finished: AtomicBool,
+}
+
+impl StartupStderr {
+    fn push(&self, line: &str) {
+        if self.finished.load(Ordering::SeqCst) {
+            return;
+        }
+        let mut lines = self.lines.lock_or_recover();
+        if lines.len() < STARTUP_STDERR_LINES {
+            lines.push(line.to_string());
+        }
+    }
+
+    /// Stops capturing and returns what was captured.
+    pub fn finish(&self) -> Vec<String> {
+        self.finished.store(true, Ordering::SeqCst);
+        std::mem::take(&mut *self.lines.lock_or_recover())
+    }
+}
+
+/// Python `logging` / uvicorn prefixes and the level each maps to.
+const LEVEL_PREFIXES: [(&str, &str); 5] = [
+    ("DEBUG:", "debug"),
+    ("INFO:", "info"),
+    ("WARNING:", "warning"),
+    ("ERROR:", "error"),
+    ("CRITICAL:", "critical"),
+];
+
+fn parse_level(line: &str) -> Option<&'static str> {
+    LEVEL_PREFIXES
+        .iter()
+        .find(|(prefix, _)| line.starts_with(prefix))
+        .map(|(_, level)| *level)
+}
+
+/// A stdout line other than the handshake.
+pub enum StdoutLine {
+    Progress(SidecarProgress),
+    /// Printed before the handshake, e.g. "importing torch".
+    Starting(String),
+    Log(String),
+}
+
+/// Reads stdout until the sidecar closes it. The first handshake line is
+/// sent over `handshake_tx`, progress lines become `sidecar-progress` events,
+/// other lines before the handshake `sidecar-starting` events, and every
+/// line after it a `sidecar-log` event.
+pub fn read_stdout(
+    app_handle: tauri::AppHandle,
+    stdout: impl Read,
+    handshake_tx: Sender<HandshakeResult>,
+) {
+    let max_lines = config::handshake_max_lines();
+    read_lines(stdout, handshake_tx, max_lines, |line| match line {
+        StdoutLine::Progress(progress) => {
+            let _ = app_handle.emit("sidecar-progress", progress);
+        }
+        StdoutLine::Starting(line) => {
+            app_handle
+                .state::<SidecarState>()
+                .recent_output
+                .push(line.clone());
+            let _ = app_handle.emit("sidecar-starting", SidecarStarting { line });
+        }
+        StdoutLine::Log(line) => {
+            app_handle
+                .state::<SidecarState>()
+                .recent_output
+                .push(line.clone());
+            let level = parse_level(&line);
+            let _ = app_handle.emit("sidecar-log", SidecarLog { line, level });
+        }
+    });
+}
+
+/// The part of `read_stdout` that needs no app: sends the handshake over
+/// `handshake_tx` (or `PortNeverReported` once stdout closes without one)
+/// and passes every line but the handshake to `on_line`. After `max_lines`
+/// lines without a handshake (0 for no limit) it sends `HandshakeNotFound`
+/// instead and stops looking, but keeps draining. Like stderr,
+/// stdout is read as bytes, so a stray non-UTF-8 byte only garbles its own
+/// line.
+pub fn read_lines(
+    stdout: impl Read,
+    handshake_tx: Sender<HandshakeResult>,
+    max_lines: usize,
+    mut on_line: impl FnMut(StdoutLine),
+) {
+    let mut reader = BufReader::new(stdout);
+    let mut handshake_sent = false;
+    let mut scanned = Vec::new();
+    let mut stage = None;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf)
+            .trim_end_matches(['\r', '\n'])
+            .to_string();
+        let starting = !handshake_sent;
+        if starting {
+            if let Some(handshake) = handshake::parse(&line) {
+                if let Err(bad) = &handshake {
+                    log::error!("Sidecar printed an invalid handshake line: {bad:?}");
+                }
+                let _ = handshake_tx.send(handshake.map_err(SidecarError::InvalidPort));
+                handshake_sent = true;
+                continue;
+            }
+            scanned.push(line.clone());
+            if scanned.len() == max_lines {
+                log::error!("No handshake in the sidecar's first {max_lines} lines of output");
+                let lines = std::mem::take(&mut scanned);
+                let _ = handshake_tx.send(Err(SidecarError::HandshakeNotFound(lines)));
+                handshake_sent = true;
+            }
+        }
+        if let Some(progress) = parse_progress(&line, &mut stage) {
+            on_line(StdoutLine::Progress(progress));
+            continue;
+        }
+        // Keep draining stdout so the pipe buffer never fills.
+        // (PyTorch on Windows blocks if the pipe read-end closes.)
+        if starting {
+            on_line(StdoutLine::Starting(line));
+        } else {
+            on_line(StdoutLine::Log(line));
+        }
+    }
+    if !handshake_sent {
+        // Typically an import error; no point waiting out the timeout.
+        let _ = handshake_tx.send(Err(SidecarError::PortNeverReported(Vec::new())));
+    }
+}
+
+/// Drains stderr until the sidecar closes it, appending each line to the
+/// log file, the recent-output buffer and `startup`. Without a log file (or in dev
+/// builds) lines also go to our own stderr, as they did when stderr was
+/// inherited. Lines are raw bytes, so invalid UTF-8 can't stop the drain.
+pub fn read_stderr(
+    app_handle: tauri::AppHandle,
+    stderr: ChildStderr,
+    mut log: Option<RotatingLog>,
+    startup: Arc<StartupStderr>,
+) {
+    let mut reader = BufReader::new(stderr);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if !line.ends_with(b"\n") {
+            line.push(b'\n');
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\r', '\n']);
+        startup.push(text);
+        app_handle
+            .state::<SidecarState>()
+            .recent_output
+            .push(text.to_string());
+        if let Some(file) = log.as_mut() {
+            if let Err(err) = file.write_line(&line) {
+                log::warn!("Failed to write sidecar log, disabling it: {err}");
+                log = None;
+            }
+        }
+        if log.is_none() || cfg!(debug_assertions) {
+            let _ = io::stderr().write_all(&line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_progress_line() {
+        let mut stage = None;
+
+        let progress = parse_progress("PROGRESS=42.5 STAGE=loading", &mut stage).unwrap();
+
+        assert_eq!(progress.percent, 42.5);
+        assert_eq!(progress.stage.as_deref(), Some("loading"));
+        assert_eq!(stage.as_deref(), Some("loading"));
+    }
+
+    #[test]
+    fn a_progress_line_keeps_the_current_stage() {
+        let mut stage = None;
+        parse_progress("STAGE=warmup", &mut stage).unwrap();
+
+        let progress = parse_progress("PROGRESS=150", &mut stage).unwrap();
+
+        assert_eq!(progress.percent, 100.0);
+        assert_eq!(progress.stage.as_deref(), Some("warmup"));
+    }
+
+    #[test]
+    fn a_bare_stage_starts_at_zero() {
+        let mut stage = Some("loading".to_string());
+
+        let progress = parse_progress("STAGE=warmup", &mut stage).unwrap();
+
+        assert_eq!(progress.percent, 0.0);
+        assert_eq!(progress.stage.as_deref(), Some("warmup"));
+    }
+
+    #[test]
+    fn a_malformed_progress_line_is_ignored() {
+        let mut stage = Some("loading".to_string());
+
+        assert!(parse_progress("PROGRESS=half STAGE=warmup", &mut stage).is_none());
+        assert_eq!(stage.as_deref(), Some("loading"));
+    }
+
+    #[test]
+    fn other_lines_are_not_progress() {
+        let mut stage = None;
+
+        assert!(parse_progress("Loading model weights", &mut stage).is_none());
+        assert!(stage.is_none());
+    }
+}
@@ -0,0 +1,121 @@
+//! Process-tree management for the sidecar. The sidecar is spawned as the
+//! leader of its own process group so that PyTorch worker processes can be
+//! signalled together with it.
+
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+
+/// Configures `command` so the spawned sidecar leads its own process group.
+#[cfg(unix)]
+pub fn use_own_process_group(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+#[cfg(windows)]
+pub fn use_own_process_group(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    use windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP;
+    command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+}
+
+/// How the sidecar is asked to exit, and how it is made to, for the logs.
+#[cfg(unix)]
+const EXIT_REQUEST: &str = "SIGTERM";
+#[cfg(unix)]
+const FORCE_KILL: &str = "SIGKILL";
+#[cfg(windows)]
+const EXIT_REQUEST: &str = "CTRL_BREAK";
+#[cfg(windows)]
+const FORCE_KILL: &str = "taskkill /F";
+
+/// Asks the sidecar tree to exit, waits up to `grace` for it to do so, then
+/// force-kills whatever is left. A zero `grace` force-kills straight away.
+/// Returns whether it exited on its own.
+pub fn shutdown_process_tree(child: &mut Child, grace: Duration) -> bool {
+    let pid = child.id();
+    if grace.is_zero() {
+        log::info!("Force-killing sidecar (pid {pid}) with {FORCE_KILL}");
+        kill_process_tree(child);
+        return false;
+    }
+    log::info!(
+        "Sending {EXIT_REQUEST} to sidecar (pid {pid}), waiting up to {}ms",
+        grace.as_millis()
+    );
+    if !request_exit(child) {
+        log::info!("Could not send {EXIT_REQUEST}, escalating to {FORCE_KILL}");
+    } else if exited_within(child, grace) {
+        return true;
+    } else {
+        log::info!(
+            "Sidecar did not exit within {}ms of {EXIT_REQUEST}, escalating to {FORCE_KILL}",
+            grace.as_millis()
+        );
+    }
+    kill_process_tree(child);
+    false
+}
+
+/// Waits up to `grace` for a sidecar that has already been asked to exit.
+/// Returns whether it did, after sweeping up any workers it left behind.
+pub fn exited_within(child: &mut Child, grace: Duration) -> bool {
+    if !wait_for_exit(child, grace) {
+        return false;
+    }
+    log::info!("Sidecar exited gracefully");
+    // Worker processes may outlive the group leader.
+    #[cfg(unix)]
+    kill_process_tree(child);
+    true
+}
+
+fn wait_for_exit(child: &mut Child, grace: Duration) -> bool {
+    let deadline = Instant::now() + grace;
+    loop {
+        if let Ok(Some(_)) = child.try_wait() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Sends SIGTERM to the sidecar's process group.
+#[cfg(unix)]
+fn request_exit(child: &mut Child) -> bool {
+    let pgid = -(child.id() as libc::pid_t);
+    unsafe { libc::kill(pgid, libc::SIGTERM) == 0 }
+}
+
+/// Sends CTRL_BREAK to the sidecar's process group. Fails when the sidecar
+/// doesn't share our console, in which case we go straight to the force kill.
+#[cfg(windows)]
+fn request_exit(child: &mut Child) -> bool {
+    use windows_sys::Win32::System::Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+    unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, child.id()) != 0 }
+}
+
+/// Immediately kills the sidecar and every process in its tree.
+#[cfg(windows)]
+pub fn kill_process_tree(child: &mut Child) {
+    use std::process::Stdio;
+    let pid = child.id();
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Immediately kills the sidecar and every process in its group.
+#[cfg(unix)]
+pub fn kill_process_tree(child: &mut Child) {
+    let pgid = -(child.id() as libc::pid_t);
+    unsafe {
+        libc::kill(pgid, libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
@@ -0,0 +1,420 @@
+//! Forwards frontend requests to the sidecar so the UI never handles the
+//! port or auth token itself, and tracks them so they can be cancelled.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tauri_plugin_http::reqwest::{
+    self,
+    header::{HeaderMap, CONTENT_TYPE},
+    Method,
+};
+use tokio::sync::{Notify, Semaphore, SemaphorePermit};
+
+use tauri::{Emitter, Manager};
+
+use crate::sync::LockExt;
+use crate::{http, idle, SidecarState};
+
+/// Default upper bound on a single proxied request.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long `cancel_all` waits for the sidecar to take the aborts.
+const CANCEL_ALL_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often a request made during a startup checks whether it is over.
+const STARTUP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Route that preloads the models and answers once they are ready.
+pub const WARMUP_PATH: &str = "/warmup";
+/// A cold first run downloads weights and computes embeddings.
+pub const WARMUP_TIMEOUT: Duration = Duration::from_secs(600);
+/// Route that reports (GET) or switches (POST) the active model.
+pub const MODEL_PATH: &str = "/model";
+/// Route that reports (GET) or switches (POST) the caption language.
+pub const LANGUAGE_PATH: &str = "/language";
+/// Route listing the models the sidecar can switch to.
+pub const MODELS_PATH: &str = "/models";
+/// Routes asking the sidecar to hold off and pick up background work.
+pub const PAUSE_PATH: &str = "/pause";
+pub const RESUME_PATH: &str = "/resume";
+/// Header carrying the request id, so the sidecar can find the job to abort.
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+/// Route prefix the sidecar exposes for aborting a request by id.
+const CANCEL_PATH: &str = "/requests";
+/// Start of the error a request gets when the queue has no room, so the
+/// frontend can tell it apart.
+pub const QUEUE_FULL_ERROR: &str = "QueueFull";
+
+/// Proxied requests that haven't finished yet, by request id.
+#[derive(Default)]
+pub struct InFlight {
+    requests: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+impl InFlight {
+    fn register(&self, id: &str) -> Arc<Notify> {
+        let cancelled = Arc::new(Notify::new());
+        self.requests
+            .lock_or_recover()
+            .insert(id.to_string(), cancelled.clone());
+        cancelled
+    }
+
+    fn remove(&self, id: &str) -> Option<Arc<Notify>> {
+        self.requests.lock_or_recover().remove(id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.lock_or_recover().is_empty()
+    }
+
+    fn ids(&self) -> Vec<String> {
+        self.requests.lock_or_recover().keys().cloned().collect()
+    }
+}
+
+/// Limits how many queued requests reach the sidecar at once, and how many
+/// may wait. Filling up emits `sidecar-queue-full`; draining to half of the
+/// capacity afterwards emits `sidecar-queue-available`.
+pub struct Queue {
+    slots: Semaphore,
+    waiting: AtomicUsize,
+    /// Most requests that may wait; 0 for no limit.
+    capacity: usize,
+    full: AtomicBool,
+}
+
+/// Counts a request as waiting for as long as it is alive.
+struct Waiting<'a> {
+    queue: &'a Queue,
+    app_handle: &'a tauri::AppHandle,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let depth = self.queue.waiting.fetch_sub(1, Ordering::SeqCst) - 1;
+        if depth <= self.queue.capacity / 2 && self.queue.full.swap(false, Ordering::SeqCst) {
+            log::info!("Request queue has room again ({depth} waiting)");
+            let _ = self.app_handle.emit("sidecar-queue-available", depth);
+        }
+    }
+}
+
+impl Queue {
+    pub fn new(concurrency: usize, capacity: usize) -> Self {
+        Self {
+            slots: Semaphore::new(concurrency.max(1)),
+            waiting: AtomicUsize::new(0),
+            capacity,
+            full: AtomicBool::new(false),
+        }
+    }
+
+    /// Requests waiting for a slot, not counting those running.
+    pub fn depth(&self) -> usize {
+        self.waiting.load(Ordering::SeqCst)
+    }
+
+    /// Waits for a slot; fails with `QUEUE_FULL_ERROR` straight away if
+    /// `capacity` requests are already waiting.
+    async fn acquire<'a>(
+        &'a self,
+        app_handle: &'a tauri::AppHandle,
+    ) -> Result<SemaphorePermit<'a>, String> {
+        let capacity = self.capacity;
+        let depth = self
+            .waiting
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |depth| {
+                (capacity == 0 || depth < capacity).then_some(depth + 1)
+            })
+            .map_err(|depth| format!("{QUEUE_FULL_ERROR}: {depth} requests already waiting"))?
+            + 1;
+        let _waiting = Waiting {
+            queue: self,
+            app_handle,
+        };
+        if depth == capacity && !self.full.swap(true, Ordering::SeqCst) {
+            log::warn!("Request queue is full ({depth} waiting)");
+            let _ = app_handle.emit("sidecar-queue-full", depth);
+        }
+        self.slots.acquire().await.map_err(|e| e.to_string())
+    }
+}
+
+/// A fresh id for a proxied request, sent as `X-Request-Id` and used in
+/// the log lines for it on both sides.
+pub fn new_request_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Port and token of the running sidecar.
+fn target(state: &SidecarState) -> Result<(u16, Option<String>), String> {
+    let handshake = state
+        .snapshot()
+        .handshake
+        .ok_or_else(|| "Sidecar is not running".to_string())?;
+    Ok((handshake.port, handshake.auth_token))
+}
+
+/// Sends `method path` with an optional JSON `body` and returns the JSON
+/// response (`null` for an empty one). Non-2xx responses become errors
+/// carrying the sidecar's own message where it gave one. The request is
+/// tracked under `request_id` until it finishes or is cancelled. A sidecar
+/// stopped for idleness is started again first.
+pub async fn call(
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    method: &str,
+    body: Option<Value>,
+    timeout: Duration,
+    request_id: &str,
+) -> Result<Value, String> {
+    let payload = body.map_or(Payload::Empty, Payload::Json);
+    dispatch(
+        app_handle, path, method, payload, timeout, request_id, false,
+    )
+    .await
+}
+
+/// Like `call`, but first waits for a slot in the request queue so no more
+/// than `NOMEN_MAX_CONCURRENT_REQUESTS` run at once. Waiting can be
+/// cancelled too.
+pub async fn call_queued(
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    method: &str,
+    body: Option<Value>,
+    timeout: Duration,
+    request_id: &str,
+) -> Result<Value, String> {
+    let payload = body.map_or(Payload::Empty, Payload::Json);
+    dispatch(app_handle, path, method, payload, timeout, request_id, true).await
+}
+
+/// Streams `file` to the sidecar as a POST to `path`, with `headers`
+/// alongside. Queued like `call_queued`.
+pub async fn upload(
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    file: tokio::fs::File,
+    headers: Vec<(&'static str, String)>,
+    timeout: Duration,
+    request_id: &str,
+) -> Result<Value, String> {
+    let payload = Payload::File { file, headers };
+    dispatch(app_handle, path, "POST", payload, timeout, request_id, true).await
+}
+
+/// What a proxied request sends.
+enum Payload {
+    Empty,
+    Json(Value),
+    File {
+        file: tokio::fs::File,
+        headers: Vec<(&'static str, String)>,
+    },
+}
+
+async fn dispatch(
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    method: &str,
+    payload: Payload,
+    timeout: Duration,
+    request_id: &str,
+    queued: bool,
+) -> Result<Value, String> {
+    let state = app_handle.state::<SidecarState>();
+    let started = Instant::now();
+    log::debug!("[{request_id}] {method} {path}");
+    let cancelled = state.in_flight.register(request_id);
+    let result = tokio::select! {
+        result = async {
+            let _slot = if queued {
+                Some(state.queue.acquire(app_handle).await?)
+            } else {
+                None
+            };
+            let request = build(app_handle, path, method, payload, timeout, request_id).await?;
+            send(request).await
+        } => result,
+        _ = cancelled.notified() => Err(format!("Request {request_id} was cancelled")),
+    };
+    state.in_flight.remove(request_id);
+    let elapsed = started.elapsed().as_millis();
+    match &result {
+        Ok(_) => log::debug!("[{request_id}] {method} {path} done in {elapsed}ms"),
+        Err(err) => log::warn!("[{request_id}] {method} {path} failed after {elapsed}ms: {err}"),
+    }
+    result
+}
+
+async fn build(
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    method: &str,
+    payload: Payload,
+    timeout: Duration,
+    request_id: &str,
+) -> Result<reqwest::RequestBuilder, String> {
+    let state = app_handle.state::<SidecarState>();
+    state.activity.touch();
+    idle::wake(app_handle).await?;
+    // The sidecar isn't published until it is ready.
+    while state.startup.is_starting() {
+        tokio::time::sleep(STARTUP_POLL_INTERVAL).await;
+    }
+    let (port, token) = target(&state)?;
+    let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+        .map_err(|_| format!("Invalid HTTP method {method:?}"))?;
+    let path = path.strip_prefix('/').unwrap_or(path);
+
+    let mut request = http::client()
+        .request(method, format!("http://127.0.0.1:{port}/{path}"))
+        .timeout(timeout)
+        .header(REQUEST_ID_HEADER, request_id)
+        .headers(state.headers.header_map());
+    if let Some(token) = token.as_deref() {
+        request = request.bearer_auth(token);
+    }
+    match payload {
+        Payload::Empty => {}
+        Payload::Json(body) => {
+            // The plugin builds reqwest without its `json` feature.
+            let bytes = serde_json::to_vec(&body).map_err(|e| e.to_string())?;
+            request = request.header(CONTENT_TYPE, "application/json").body(bytes);
+        }
+        Payload::File { file, headers } => {
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+            request = request
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .body(file);
+        }
+    }
+    Ok(request)
+}
+
+async fn send(request: reqwest::RequestBuilder) -> Result<Value, String> {
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    let value = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).map_err(|e| format!("Invalid JSON from sidecar: {e}"))?
+    };
+    if !status.is_success() {
+        let message = value
+            .get("error")
+            .and_then(Value::as_str)
+            .map_or_else(|| status.to_string(), str::to_string);
+        return Err(message);
+    }
+    Ok(value)
+}
+
+/// Stops waiting on `request_id` and asks the sidecar to abort it. Returns
+/// whether the request was still in flight.
+pub async fn cancel(state: &SidecarState, request_id: &str) -> Result<bool, String> {
+    let Some(cancelled) = state.in_flight.remove(request_id) else {
+        return Ok(false);
+    };
+    cancelled.notify_one();
+
+    let (port, token) = target(state)?;
+    abort(
+        state.headers.header_map(),
+        port,
+        token,
+        request_id.to_string(),
+        REQUEST_TIMEOUT,
+    )
+    .await;
+    Ok(true)
+}
+
+/// Asks the sidecar on `port` to abort `request_id`, logging a failure.
+async fn abort(
+    headers: HeaderMap,
+    port: u16,
+    token: Option<String>,
+    request_id: String,
+    timeout: Duration,
+) {
+    let mut request = http::client()
+        .delete(format!("http://127.0.0.1:{port}{CANCEL_PATH}/{request_id}"))
+        .timeout(timeout)
+        .headers(headers);
+    if let Some(token) = token.as_deref() {
+        request = request.bearer_auth(token);
+    }
+    if let Err(err) = request.send().await {
+        log::warn!("Failed to abort sidecar request {request_id}: {err}");
+    }
+}
+
+/// POSTs `path` to the sidecar if one is running, without waking a stopped
+/// one or counting as activity. A sidecar without the route (404) is fine.
+pub async fn notify(state: &SidecarState, path: &str) -> Result<(), String> {
+    let (port, token) = target(state)?;
+    let mut request = http::client()
+        .post(format!("http://127.0.0.1:{port}{path}"))
+        .timeout(REQUEST_TIMEOUT)
+        .headers(state.headers.header_map());
+    if let Some(token) = token.as_deref() {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        log::debug!("Sidecar has no {path} route");
+    } else if !status.is_success() {
+        return Err(status.to_string());
+    }
+    Ok(())
+}
+
+/// Cancels every in-flight request. The sidecar is asked to abort them all
+/// at once, and this blocks at most `CANCEL_ALL_TIMEOUT` for the answers:
+/// it runs on the main thread as the window closes.
+pub fn cancel_all(state: &SidecarState) {
+    let ids: Vec<String> = state
+        .in_flight
+        .ids()
+        .into_iter()
+        .filter(|id| match state.in_flight.remove(id) {
+            Some(cancelled) => {
+                cancelled.notify_one();
+                true
+            }
+            None => false,
+        })
+        .collect();
+    if ids.is_empty() {
+        return;
+    }
+    let Ok((port, token)) = target(state) else {
+        return;
+    };
+    let headers = state.headers.header_map();
+    let aborts: Vec<_> = ids
+        .into_iter()
+        .map(|id| {
+            tauri::async_runtime::spawn(abort(
+                headers.clone(),
+                port,
+                token.clone(),
+                id,
+                CANCEL_ALL_TIMEOUT,
+            ))
+        })
+        .collect();
+    tauri::async_runtime::block_on(async {
+        for abort in aborts {
+            let _ = abort.await;
+        }
+    });
+}
@@ -0,0 +1,68 @@
+//! Where the app looks for the sidecar, for `get_resource_paths`. Both the
+//! dev and the production locations are reported whichever this build uses,
+//! so a broken bundle can be compared against a working checkout.
+
+use std::path::PathBuf;
+
+use tauri::Manager;
+
+/// A path the app resolved and whether anything is there.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Location {
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+impl Location {
+    fn new(path: PathBuf) -> Self {
+        let exists = path.exists();
+        Self { path, exists }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ResourcePaths {
+    /// `"dev"` or `"production"`: whether this build runs the sidecar from
+    /// `project_root` or from a bundled binary.
+    pub mode: &'static str,
+    /// `None` when Tauri can't resolve it; `resource_dir_error` says why.
+    pub resource_dir: Option<Location>,
+    pub resource_dir_error: Option<String>,
+    /// Every place the bundled binary is looked for, in order; the first
+    /// that exists is used.
+    pub sidecar_binaries: Vec<Location>,
+    /// The repo checkout dev mode runs from; `None` when no
+    /// `pyproject.toml` was found.
+    pub project_root: Option<Location>,
+    /// `src/` under `project_root`, put on the dev sidecar's `PYTHONPATH`.
+    pub python_path: Option<Location>,
+}
+
+pub fn resolve(app_handle: &tauri::AppHandle) -> ResourcePaths {
+    let (resource_dir, resource_dir_error) = match app_handle.path().resource_dir() {
+        Ok(dir) => (Some(dir), None),
+        Err(err) => (None, Some(err.to_string())),
+    };
+    let sidecar_binaries = resource_dir
+        .as_deref()
+        .map(crate::sidecar_binary_candidates)
+        .unwrap_or_default()
+        .into_iter()
+        .map(Location::new)
+        .collect();
+    let project_root = crate::find_project_root().ok();
+    ResourcePaths {
+        mode: if cfg!(debug_assertions) {
+            "dev"
+        } else {
+            "production"
+        },
+        resource_dir: resource_dir.map(Location::new),
+        resource_dir_error,
+        sidecar_binaries,
+        python_path: project_root
+            .as_ref()
+            .map(|root| Location::new(root.join("src"))),
+        project_root: project_root.map(Location::new),
+    }
+}
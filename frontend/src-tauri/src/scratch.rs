@@ -0,0 +1,51 @@
+//! A scratch directory for the sidecar's intermediate files (temp audio
+//! chunks and the like) under the app cache dir, so they don't pile up in
+//! the user's temp directory. The sidecar is told about it through
+//! `NOMEN_SCRATCH_DIR`; the directory is emptied on a clean close.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tauri::Manager;
+
+/// Env var the sidecar reads its scratch directory from.
+pub const SCRATCH_ENV: &str = "NOMEN_SCRATCH_DIR";
+
+/// Directory name inside the app cache dir.
+const SCRATCH_DIR: &str = "scratch";
+
+/// The scratch directory, created if needed; `None` if the cache dir can't
+/// be resolved or created.
+pub fn dir(app_handle: &tauri::AppHandle) -> Option<PathBuf> {
+    let dir = app_handle.path().app_cache_dir().ok()?.join(SCRATCH_DIR);
+    match fs::create_dir_all(&dir) {
+        Ok(()) => Some(dir),
+        Err(err) => {
+            log::warn!("Cannot create scratch dir {}: {err}", dir.display());
+            None
+        }
+    }
+}
+
+/// Removes everything inside `dir`, leaving the directory itself. Symlinks
+/// are removed, never followed, so nothing outside it is touched. Entries
+/// that can't be removed (e.g. still open on Windows) are logged and
+/// skipped. Returns how many entries were removed.
+pub fn clear(dir: &Path) -> io::Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_dir = fs::symlink_metadata(&path).is_ok_and(|meta| meta.is_dir());
+        let result = if is_dir {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        match result {
+            Ok(()) => removed += 1,
+            Err(err) => log::warn!("Cannot remove scratch file {}: {err}", path.display()),
+        }
+    }
+    Ok(removed)
+}
@@ -0,0 +1,135 @@
+//! A smoke test of the whole sidecar lifecycle for release checks: spawn a
+//! throwaway instance, wait for its handshake and readiness, query
+//! `/health`, and shut it down. The managed sidecars, their output buffer
+//! and their state are not touched.
+
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use tauri::Manager;
+
+use crate::process::kill_process_tree;
+use crate::timings::millis;
+use crate::{config, health, launcher, output, SidecarState};
+
+/// Instance name of the throwaway sidecar.
+const SELF_TEST_INSTANCE: &str = "self-test";
+/// How long the single `/health` request may take.
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of one step of the self-test.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SelfTestStep {
+    pub name: &'static str,
+    pub ok: bool,
+    pub ms: u64,
+    pub error: Option<String>,
+}
+
+/// What `sidecar_self_test` returns. Steps that can't run after a failure
+/// are left out, but a sidecar that was spawned is always shut down.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub steps: Vec<SelfTestStep>,
+}
+
+/// Collects the steps as they run.
+struct Steps(Vec<SelfTestStep>);
+
+impl Steps {
+    /// Runs `step`, recording its outcome; `None` if it failed.
+    fn run<T>(
+        &mut self,
+        name: &'static str,
+        step: impl FnOnce() -> Result<T, String>,
+    ) -> Option<T> {
+        let started = Instant::now();
+        let result = step();
+        let ms = millis(started.elapsed());
+        match &result {
+            Ok(_) => log::info!("Self-test: {name} ok in {ms}ms"),
+            Err(err) => log::warn!("Self-test: {name} failed after {ms}ms: {err}"),
+        }
+        self.0.push(SelfTestStep {
+            name,
+            ok: result.is_ok(),
+            ms,
+            error: result.as_ref().err().cloned(),
+        });
+        result.ok()
+    }
+}
+
+/// Runs the self-test. Whatever fails, no process is left running.
+pub fn run(app_handle: &tauri::AppHandle) -> SelfTestReport {
+    let mut steps = Steps(Vec::new());
+    run_steps(app_handle, &mut steps);
+    let passed = steps.0.len() == 5 && steps.0.iter().all(|step| step.ok);
+    log::info!("Self-test {}", if passed { "passed" } else { "failed" });
+    SelfTestReport {
+        passed,
+        steps: steps.0,
+    }
+}
+
+fn run_steps(app_handle: &tauri::AppHandle, steps: &mut Steps) {
+    let timeout = config::startup_timeout();
+    let Some((mut child, token)) = steps.run("spawn", || {
+        let (mut command, token) =
+            crate::sidecar_command(app_handle, SELF_TEST_INSTANCE, None, None)
+                .map_err(|e| e.to_string())?;
+        // A fixed port would collide with the managed sidecar.
+        command.env_remove(config::FIXED_PORT_ENV);
+        let mut child = launcher::spawn(&mut command).map_err(|e| e.to_string())?;
+        if let Some(mut stderr) = child.stderr.take() {
+            std::thread::spawn(move || io::copy(&mut stderr, &mut io::sink()));
+        }
+        Ok((child, token))
+    }) else {
+        return;
+    };
+    let deadline = Instant::now() + timeout;
+    // `cancel_sidecar_startup` is for the managed sidecar, not this one.
+    let never = AtomicBool::new(false);
+
+    let Some(mut handshake) = steps.run("handshake", || {
+        let read =
+            |stdout, tx| output::read_lines(stdout, tx, config::handshake_max_lines(), |_| {});
+        let handshake = launcher::await_handshake(&mut child, read, deadline, timeout, &never)
+            .map_err(|e| e.to_string())?;
+        if let Err(err) = handshake.check_version() {
+            kill_process_tree(&mut child);
+            return Err(err.to_string());
+        }
+        Ok(handshake)
+    }) else {
+        return;
+    };
+    let port = handshake.port;
+    let headers = &app_handle.state::<SidecarState>().headers;
+
+    let ready = steps.run("ready", || {
+        health::wait_until_ready(port, Some(&token), headers, deadline, timeout, &never)
+            .map_err(|e| e.to_string())
+    });
+    if ready.is_some() {
+        steps.run("health", || {
+            if health::is_healthy(port, Some(&token), headers, HEALTH_TIMEOUT) {
+                Ok(())
+            } else {
+                Err(format!("{} did not answer", health::health_path()))
+            }
+        });
+    }
+
+    handshake.auth_token = Some(token);
+    steps.run("shutdown", || {
+        if crate::stop_sidecar(Some(&handshake), headers, &mut child) {
+            Ok(())
+        } else {
+            Err("Sidecar had to be force-killed".to_string())
+        }
+    });
+}
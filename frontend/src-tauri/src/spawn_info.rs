@@ -0,0 +1,66 @@
+//! The command line the last sidecar was launched with, kept for
+//! `get_spawn_info` so "why didn't my flags take effect" is answerable
+//! without a debugger.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::auth;
+
+/// Stands in for values that mustn't leave the process, like the token.
+const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SpawnInfo {
+    pub instance: String,
+    pub program: String,
+    pub args: Vec<String>,
+    /// `None` when the sidecar runs in the app's own working directory.
+    pub cwd: Option<PathBuf>,
+    /// The `NOMEN_*` variables the sidecar sees, inherited or set by the
+    /// app, with the auth token redacted.
+    pub env: BTreeMap<String, String>,
+}
+
+impl SpawnInfo {
+    /// Describes `command` as it would run now. Both the inherited and the
+    /// explicitly set `NOMEN_*` variables count; `NOMEN_CLEAN_ENV` keeps
+    /// them all, so the result is the same either way.
+    pub fn capture(instance: &str, command: &Command) -> Self {
+        let mut vars: BTreeMap<String, String> = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+            .filter(|(name, _)| is_nomen_var(name))
+            .map(|(name, value)| (name, value.to_string_lossy().into_owned()))
+            .collect();
+        for (name, value) in command.get_envs() {
+            let name = name.to_string_lossy();
+            if !is_nomen_var(&name) {
+                continue;
+            }
+            match value {
+                Some(value) => vars.insert(name.into_owned(), value.to_string_lossy().into_owned()),
+                None => vars.remove(name.as_ref()),
+            };
+        }
+        if let Some(token) = vars.get_mut(auth::TOKEN_ENV) {
+            *token = REDACTED.to_string();
+        }
+
+        Self {
+            instance: instance.to_string(),
+            program: command.get_program().to_string_lossy().into_owned(),
+            args: command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            cwd: command.get_current_dir().map(PathBuf::from),
+            env: vars,
+        }
+    }
+}
+
+fn is_nomen_var(name: &str) -> bool {
+    name.starts_with("NOMEN_")
+}
@@ -0,0 +1,51 @@
+//! Aborting a sidecar startup that hangs. Startups in progress are counted;
+//! `cancel` raises a flag their handshake and readiness waits poll, and the
+//! flag is lowered again once the last of them has given up.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+#[derive(Default)]
+pub struct Cancel {
+    in_progress: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+/// Counts as a startup in progress until dropped.
+pub struct InProgress<'a>(&'a Cancel);
+
+impl Cancel {
+    pub fn begin(&self) -> InProgress<'_> {
+        if self.in_progress.fetch_add(1, Ordering::SeqCst) == 0 {
+            // A cancel that raced the end of the previous startup.
+            self.cancelled.store(false, Ordering::SeqCst);
+        }
+        InProgress(self)
+    }
+
+    /// Asks every startup in progress to give up. Returns whether there was
+    /// one.
+    pub fn cancel(&self) -> bool {
+        if self.in_progress.load(Ordering::SeqCst) == 0 {
+            return false;
+        }
+        self.cancelled.store(true, Ordering::SeqCst);
+        true
+    }
+
+    pub fn is_starting(&self) -> bool {
+        self.in_progress.load(Ordering::SeqCst) > 0
+    }
+
+    /// The flag the startup waits poll.
+    pub fn flag(&self) -> &AtomicBool {
+        &self.cancelled
+    }
+}
+
+impl Drop for InProgress<'_> {
+    fn drop(&mut self) {
+        if self.0.in_progress.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.cancelled.store(false, Ordering::SeqCst);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Mutex access that survives a panic elsewhere. A thread that panics while
+//! holding a lock poisons it, and unwrapping every later `lock()` would turn
+//! that one panic into a failure of every command touching the same state.
+//! The guarded data here stays consistent across a panic (each update is a
+//! single assignment or collection call), so the lock is taken over instead.
+
+use std::sync::{Mutex, MutexGuard};
+
+pub trait LockExt<T> {
+    /// Locks, recovering (with a warning) from a poisoned mutex.
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            log::warn!("Recovering a lock poisoned by a panicked thread");
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+}
@@ -0,0 +1,46 @@
+//! What the sidecar reports about its host and runtime on `/system`. It
+//! doesn't change while the app runs, so the first answer is kept.
+
+use serde_json::{Map, Value};
+use tauri::Manager;
+
+use crate::sync::LockExt;
+use crate::{proxy, SidecarState};
+
+/// Route reporting the sidecar's host and runtime details.
+pub const SYSTEM_PATH: &str = "/system";
+
+/// The `/system` response. Fields the shell knows about are typed; the
+/// rest pass through in `extra`, so the frontend sees the JSON unchanged.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SystemInfo {
+    pub version: Option<String>,
+    pub python_version: Option<String>,
+    pub platform: Option<String>,
+    pub torch_version: Option<String>,
+    pub cuda_available: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The cached system info, fetched from the sidecar on first use.
+pub async fn get(app_handle: &tauri::AppHandle) -> Result<SystemInfo, String> {
+    let state = app_handle.state::<SidecarState>();
+    if let Some(info) = state.system_info.lock_or_recover().clone() {
+        return Ok(info);
+    }
+    let request_id = proxy::new_request_id();
+    let response = proxy::call(
+        app_handle,
+        SYSTEM_PATH,
+        "GET",
+        None,
+        proxy::REQUEST_TIMEOUT,
+        &request_id,
+    )
+    .await?;
+    let info: SystemInfo = serde_json::from_value(response)
+        .map_err(|e| format!("Invalid system info from sidecar: {e}"))?;
+    *state.system_info.lock_or_recover() = Some(info.clone());
+    Ok(info)
+}
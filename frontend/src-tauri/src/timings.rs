@@ -0,0 +1,101 @@
+//! How long each phase of a sidecar startup took: spawn, first stdout
+//! output, handshake, and the first accepted readiness probe. Separates
+//! Python import time (spawn to first output) from app and model setup
+//! (handshake to ready).
+
+use std::io::{self, Read};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Milestones of the last startup, in milliseconds since the spawn.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct StartupTimings {
+    /// `None` if the sidecar printed nothing before its handshake arrived.
+    pub first_output_ms: Option<u64>,
+    pub handshake_ms: u64,
+    pub ready_ms: u64,
+}
+
+/// Times one startup.
+pub struct StartupClock {
+    spawned: Instant,
+    first_output: Arc<OnceLock<Instant>>,
+    handshake: Option<Instant>,
+}
+
+impl StartupClock {
+    /// Starts the clock; call right before spawning.
+    pub fn start() -> Self {
+        Self {
+            spawned: Instant::now(),
+            first_output: Arc::default(),
+            handshake: None,
+        }
+    }
+
+    /// A handle for timing the child's stdout from its reader thread.
+    pub fn first_output(&self) -> FirstOutput {
+        FirstOutput(self.first_output.clone())
+    }
+
+    pub fn handshake_received(&mut self) {
+        self.handshake = Some(Instant::now());
+    }
+
+    /// Stops the clock at readiness and logs the phases.
+    pub fn ready(&self) -> StartupTimings {
+        let ready = Instant::now();
+        let handshake = self.handshake.unwrap_or(ready);
+        let since_spawn = |at: Instant| millis(at.duration_since(self.spawned));
+        let timings = StartupTimings {
+            first_output_ms: self.first_output.get().map(|at| since_spawn(*at)),
+            handshake_ms: since_spawn(handshake),
+            ready_ms: since_spawn(ready),
+        };
+        let first_output = timings
+            .first_output_ms
+            .map_or("none".to_string(), |ms| format!("{ms}ms"));
+        log::info!(
+            "Sidecar startup: first output {first_output}, handshake +{}ms, ready +{}ms (total {}ms)",
+            millis(handshake.duration_since(
+                self.first_output.get().copied().unwrap_or(self.spawned)
+            )),
+            millis(ready.duration_since(handshake)),
+            timings.ready_ms,
+        );
+        timings
+    }
+}
+
+pub fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Where a `FirstRead` records its time.
+pub struct FirstOutput(Arc<OnceLock<Instant>>);
+
+impl FirstOutput {
+    /// Wraps the child's stdout so its first bytes are timed.
+    pub fn watch<R: Read>(self, stdout: R) -> FirstRead<R> {
+        FirstRead {
+            inner: stdout,
+            at: self.0,
+        }
+    }
+}
+
+/// A reader that records when it first returned data.
+pub struct FirstRead<R> {
+    inner: R,
+    at: Arc<OnceLock<Instant>>,
+}
+
+impl<R: Read> Read for FirstRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read > 0 {
+            let _ = self.at.set(Instant::now());
+        }
+        Ok(read)
+    }
+}
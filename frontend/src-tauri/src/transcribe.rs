@@ -0,0 +1,118 @@
+//! Sending an audio file from disk to the sidecar's `/transcribe` route,
+//! which describes it in words. The shell checks the file and streams it,
+//! so the frontend needs neither the port nor direct file access; the text
+//! arrives later as a `sidecar-transcription` event.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::Emitter;
+
+use crate::{audio, config, proxy};
+
+/// Route that describes an uploaded audio file.
+const TRANSCRIBE_PATH: &str = "/transcribe";
+/// Captioning loads a model on first use, so allow well beyond
+/// `proxy::REQUEST_TIMEOUT`.
+const TRANSCRIBE_TIMEOUT: Duration = Duration::from_secs(300);
+/// Headers carrying the file name (for its extension) and the options.
+const FILENAME_HEADER: &str = "X-Filename";
+const OPTIONS_HEADER: &str = "X-Transcribe-Options";
+
+/// Payload of the `sidecar-transcription` event: the text, or why there is
+/// none.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Transcription {
+    pub request_id: String,
+    pub text: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Checks `path` and starts streaming it to the sidecar, returning the
+/// request id the result will be reported under (and that
+/// `cancel_sidecar_request` takes).
+pub fn start(
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    options: serde_json::Value,
+) -> Result<String, String> {
+    let path = validate(Path::new(path))?;
+    let request_id = proxy::new_request_id();
+    let handle = app_handle.clone();
+    let id = request_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = send(&handle, &path, &options, &id).await;
+        if let Err(err) = &result {
+            log::warn!("Transcription of {} failed: {err}", path.display());
+        }
+        let (text, error) = match result {
+            Ok(text) => (Some(text), None),
+            Err(err) => (None, Some(err)),
+        };
+        let event = Transcription {
+            request_id: id,
+            text,
+            error,
+        };
+        let _ = handle.emit("sidecar-transcription", event);
+    });
+    Ok(request_id)
+}
+
+/// The canonical path of `path` if it is an existing file inside
+/// `NOMEN_ALLOWED_DIRS` (when that is set) that `audio::is_supported_audio`
+/// accepts.
+fn validate(path: &Path) -> Result<PathBuf, String> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|err| format!("Cannot open {}: {err}", path.display()))?;
+    if !canonical.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    let allowed = config::allowed_dirs();
+    let inside = |dir: &PathBuf| {
+        dir.canonicalize()
+            .is_ok_and(|dir| canonical.starts_with(dir))
+    };
+    if !allowed.is_empty() && !allowed.iter().any(inside) {
+        return Err(format!(
+            "{} is outside the directories allowed by NOMEN_ALLOWED_DIRS",
+            path.display()
+        ));
+    }
+    audio::is_supported_audio(&canonical)?;
+    Ok(canonical)
+}
+
+async fn send(
+    app_handle: &tauri::AppHandle,
+    path: &Path,
+    options: &serde_json::Value,
+    request_id: &str,
+) -> Result<String, String> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|err| format!("Cannot open {}: {err}", path.display()))?;
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let headers = vec![
+        (FILENAME_HEADER, filename),
+        (OPTIONS_HEADER, options.to_string()),
+    ];
+    let response = proxy::upload(
+        app_handle,
+        TRANSCRIBE_PATH,
+        file,
+        headers,
+        TRANSCRIBE_TIMEOUT,
+        request_id,
+    )
+    .await?;
+    response
+        .get("text")
+        .and_then(serde_json::Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "Sidecar did not return a transcription".to_string())
+}
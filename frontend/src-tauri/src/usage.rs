@@ -0,0 +1,144 @@
+//! Periodic CPU and memory readings of the running sidecar, emitted as
+//! `sidecar-resource-usage` events for a live performance view. Off unless
+//! `NOMEN_RESOURCE_USAGE_MS` is set.
+
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use tauri::{Emitter, Manager};
+
+use crate::SidecarState;
+
+/// Payload of the `sidecar-resource-usage` event.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ResourceUsage {
+    /// Share of one core over the last interval; above 100 when several
+    /// cores are busy.
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+}
+
+/// The previous sample: whose it was, its CPU time so far, and when.
+struct Reading {
+    pid: u32,
+    cpu: Duration,
+    at: Instant,
+}
+
+/// Samples the sidecar every `interval` for the lifetime of the app. The
+/// pid the sidecar announced in its handshake is preferred over the child's,
+/// which in dev mode is `uv` rather than Python. A sidecar that is down is
+/// skipped; a restarted one is picked up under its new pid.
+pub fn watch(app_handle: &tauri::AppHandle, interval: Duration) {
+    let state = app_handle.state::<SidecarState>();
+    let mut last: Option<Reading> = None;
+    loop {
+        std::thread::sleep(interval);
+        if state.shutting_down.load(Ordering::SeqCst) {
+            return;
+        }
+        let snapshot = state.snapshot();
+        let pid = snapshot
+            .handshake
+            .as_ref()
+            .and_then(|handshake| handshake.pid)
+            .or(snapshot.pid)
+            .filter(|_| snapshot.running);
+        let Some((pid, (cpu, rss_bytes))) = pid.and_then(|pid| Some((pid, read(pid)?))) else {
+            last = None;
+            continue;
+        };
+        let at = Instant::now();
+        // The first reading of a process only sets the baseline.
+        if let Some(previous) = last.filter(|previous| previous.pid == pid) {
+            let wall = at.duration_since(previous.at).as_secs_f64();
+            let used = cpu.saturating_sub(previous.cpu).as_secs_f64();
+            let usage = ResourceUsage {
+                cpu_percent: if wall > 0.0 { used / wall * 100.0 } else { 0.0 },
+                rss_bytes,
+            };
+            let _ = app_handle.emit("sidecar-resource-usage", usage);
+        }
+        last = Some(Reading { pid, cpu, at });
+    }
+}
+
+/// CPU time used so far and resident set size of `pid`, from `/proc`.
+#[cfg(target_os = "linux")]
+fn read(pid: u32) -> Option<(Duration, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name can contain spaces; the fields after it can't.
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let ticks: u64 = fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?;
+    let statm = std::fs::read_to_string(format!("/proc/{pid}/statm")).ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let (ticks_per_sec, page_size) = unsafe {
+        (
+            libc::sysconf(libc::_SC_CLK_TCK),
+            libc::sysconf(libc::_SC_PAGESIZE),
+        )
+    };
+    let ticks_per_sec = u64::try_from(ticks_per_sec).ok().filter(|tps| *tps > 0)?;
+    let cpu = Duration::from_secs_f64(ticks as f64 / ticks_per_sec as f64);
+    Some((cpu, pages * u64::try_from(page_size).ok()?))
+}
+
+/// The same from `ps`, whose `time` column reads `[[dd-]hh:]mm:ss.ss`.
+#[cfg(target_os = "macos")]
+fn read(pid: u32) -> Option<(Duration, u64)> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "time=,rss=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut columns = text.split_whitespace();
+    let time = columns.next()?;
+    let rss_kb: u64 = columns.next()?.parse().ok()?;
+    let (days, clock) = time.split_once('-').unwrap_or(("0", time));
+    let mut seconds = days.parse::<f64>().ok()? * 86_400.0;
+    for (part, scale) in clock.rsplit(':').zip([1.0, 60.0, 3600.0]) {
+        seconds += part.parse::<f64>().ok()? * scale;
+    }
+    Some((Duration::from_secs_f64(seconds), rss_kb * 1024))
+}
+
+#[cfg(windows)]
+fn read(pid: u32) -> Option<(Duration, u64)> {
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME};
+    use windows_sys::Win32::System::ProcessStatus::{
+        K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if process.is_null() {
+        return None;
+    }
+    let zero = FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    };
+    let (mut created, mut exited, mut kernel, mut user) = (zero, zero, zero, zero);
+    let mut memory: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    memory.cb = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    let ok = unsafe {
+        GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user) != 0
+            && K32GetProcessMemoryInfo(process, &mut memory, memory.cb) != 0
+    };
+    unsafe { CloseHandle(process) };
+    if !ok {
+        return None;
+    }
+    // FILETIMEs count 100ns intervals.
+    let hundred_ns =
+        |time: FILETIME| (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime);
+    let cpu = Duration::from_nanos((hundred_ns(kernel) + hundred_ns(user)) * 100);
+    Some((cpu, memory.WorkingSetSize as u64))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn read(_pid: u32) -> Option<(Duration, u64)> {
+    None
+}
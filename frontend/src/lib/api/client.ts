@@ -1,4 +1,4 @@
-import { sidecarFetch, getSidecarPort } from '$lib/sidecar';
+import { sidecarFetch, getSidecarEndpoint, getSidecarToken } from '$lib/sidecar';
 import type {
 	FileRecord,
 	ImportResponse,
@@ -213,8 +213,10 @@ export function resetDatabase(): Promise<{ status: string }> {
 // ---------------------------------------------------------------------------
 
 export async function getAudioUrl(id: string): Promise<string> {
-	const port = await getSidecarPort();
-	return `http://127.0.0.1:${port}/files/${id}/audio`;
+	const endpoint = await getSidecarEndpoint();
+	const token = await getSidecarToken();
+	const query = token ? `?token=${encodeURIComponent(token)}` : '';
+	return `${endpoint}/files/${id}/audio${query}`;
 }
 
 // ---------------------------------------------------------------------------
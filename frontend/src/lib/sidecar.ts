@@ -1,15 +1,272 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { fetch as tauriFetch } from '@tauri-apps/plugin-http';
 
 let cachedPort: number | null = null;
+let cachedEndpoint: string | null = null;
+let cachedToken: string | null | undefined;
+let restartListener: Promise<() => void> | null = null;
+let idleListener: Promise<() => void> | null = null;
+let stopListener: Promise<() => void> | null = null;
+let lastActivityReport = 0;
+// How often direct fetches tell the shell's idle timer the sidecar is in use.
+const ACTIVITY_REPORT_INTERVAL_MS = 5000;
 
+function watchSidecar() {
+	// The shell respawns a crashed sidecar on a new port — follow it.
+	restartListener ??= listen<number>('sidecar-restarted', (event) => {
+		cachedPort = event.payload;
+		cachedEndpoint = null;
+		// Each spawn gets a fresh auth token.
+		cachedToken = undefined;
+	});
+	// With NOMEN_IDLE_SHUTDOWN_SECS the shell stops an unused sidecar, and
+	// shutdownSidecar stops it on request; asking for the port again starts
+	// a new one.
+	idleListener ??= listen('sidecar-idle-stopped', forgetSidecar);
+	stopListener ??= listen('sidecar-stopped', forgetSidecar);
+}
+
+/** How get_sidecar_port fails; branch on `kind`, show `message`. */
+export interface PortError {
+	kind: 'not_started' | 'starting' | 'crashed' | 'disabled';
+	message: string;
+}
+
+/** How often getSidecarPort asks again while the engine is starting. */
+const STARTING_POLL_MS = 250;
+
+export function isPortError(err: unknown): err is PortError {
+	return typeof err === 'object' && err !== null && 'kind' in err && 'message' in err;
+}
+
+/** Waits out a startup in progress; other failures reject with a PortError. */
 export async function getSidecarPort(): Promise<number> {
 	if (cachedPort !== null) return cachedPort;
-	cachedPort = await invoke<number>('get_sidecar_port');
-	return cachedPort;
+	watchSidecar();
+	for (;;) {
+		try {
+			cachedPort = await invoke<number>('get_sidecar_port');
+			return cachedPort;
+		} catch (err) {
+			if (!isPortError(err) || err.kind !== 'starting') throw err;
+		}
+		await new Promise((resolve) => setTimeout(resolve, STARTING_POLL_MS));
+	}
+}
+
+/** Base URL of the engine, e.g. `http://127.0.0.1:51234`. */
+export async function getSidecarEndpoint(): Promise<string> {
+	if (cachedEndpoint !== null) return cachedEndpoint;
+	watchSidecar();
+	cachedEndpoint = await invoke<string>('get_sidecar_endpoint');
+	return cachedEndpoint;
+}
+
+function forgetSidecar() {
+	cachedPort = null;
+	cachedEndpoint = null;
+	cachedToken = undefined;
+}
+
+/** Replaces the engine with a fresh one, or starts it if startup failed. */
+export async function restartSidecar(): Promise<void> {
+	await invoke('restart_sidecar');
+	forgetSidecar();
+}
+
+/** Stops the engine but keeps the app open; the next request starts it again. */
+export function shutdownSidecar(): Promise<void> {
+	return invoke('shutdown_sidecar');
+}
+
+/**
+ * Gives up on an engine that is still starting; it then counts as stopped.
+ * Resolves to whether a startup was in progress.
+ */
+export function cancelSidecarStartup(): Promise<boolean> {
+	return invoke('cancel_sidecar_startup');
+}
+
+/** Payload of `sidecar-resource-usage`, emitted with NOMEN_RESOURCE_USAGE_MS. */
+export interface ResourceUsage {
+	cpu_percent: number;
+	rss_bytes: number;
+}
+
+export interface MemoryWarning {
+	available_mb: number;
+	required_mb: number;
+}
+
+/** Set when the engine was started with less free RAM than it wants. */
+export function getMemoryWarning(): Promise<MemoryWarning | null> {
+	return invoke<MemoryWarning | null>('get_memory_warning');
+}
+
+export interface SelfTestStep {
+	name: 'spawn' | 'handshake' | 'ready' | 'health' | 'shutdown';
+	ok: boolean;
+	ms: number;
+	error: string | null;
+}
+
+export interface SelfTestReport {
+	passed: boolean;
+	steps: SelfTestStep[];
+}
+
+/** Starts, probes and stops a throwaway engine; the running one is untouched. */
+export function sidecarSelfTest(): Promise<SelfTestReport> {
+	return invoke<SelfTestReport>('sidecar_self_test');
+}
+
+export interface SystemInfo {
+	version: string | null;
+	python_version: string | null;
+	platform: string | null;
+	torch_version: string | null;
+	cuda_available: boolean | null;
+	[key: string]: unknown;
+}
+
+/** Python, torch and host details from the engine; fixed for the session. */
+export function getSidecarSystemInfo(): Promise<SystemInfo> {
+	return invoke<SystemInfo>('get_sidecar_system_info');
+}
+
+/** Payload of `sidecar-transcription`, emitted when a transcribeFile call finishes. */
+export interface Transcription {
+	request_id: string;
+	text: string | null;
+	error: string | null;
+}
+
+/**
+ * Streams an audio file to the engine for a caption. Resolves to the request
+ * id at once; the result arrives as a `sidecar-transcription` event.
+ */
+export function transcribeFile(path: string, options: Record<string, unknown> = {}): Promise<string> {
+	return invoke<string>('transcribe_file', { path, options });
+}
+
+/** Optional features the running engine build supports. */
+export function getSidecarCapabilities(): Promise<string[]> {
+	return invoke<string[]>('get_sidecar_capabilities');
+}
+
+/** Round-trips the Tauri command bridge only; works with the sidecar down. */
+export function ping(): Promise<string> {
+	return invoke<string>('ping');
+}
+
+/** Whether the shell was started with NOMEN_DISABLE_SIDECAR (UI-only mode). */
+export async function isSidecarDisabled(): Promise<boolean> {
+	const status = await invoke<{ disabled: boolean }>('get_sidecar_status');
+	return status.disabled;
+}
+
+export async function getSidecarToken(): Promise<string | null> {
+	if (cachedToken === undefined) {
+		cachedToken = await invoke<string | null>('get_sidecar_token');
+	}
+	return cachedToken;
 }
 
 export async function sidecarFetch(path: string, init?: RequestInit): Promise<Response> {
-	const port = await getSidecarPort();
-	return tauriFetch(`http://127.0.0.1:${port}${path}`, init);
+	const endpoint = await getSidecarEndpoint();
+	const token = await getSidecarToken();
+	const now = Date.now();
+	if (now - lastActivityReport > ACTIVITY_REPORT_INTERVAL_MS) {
+		lastActivityReport = now;
+		invoke('mark_sidecar_active').catch(() => {});
+	}
+	const headers = new Headers(init?.headers);
+	if (token) headers.set('Authorization', `Bearer ${token}`);
+	return tauriFetch(`${endpoint}${path}`, { ...init, headers });
+}
+
+/** A `callSidecar` result with the id it was logged under on both sides. */
+export interface SidecarResponse<T> {
+	request_id: string;
+	body: T;
+}
+
+/**
+ * Sends a JSON request through the shell, which adds the port and auth token.
+ * Errors end with "(request <id>)" for matching them against the logs.
+ */
+export function callSidecar<T>(
+	path: string,
+	method = 'GET',
+	body?: unknown,
+	options: { timeoutMs?: number; requestId?: string } = {}
+): Promise<SidecarResponse<T>> {
+	return invoke<SidecarResponse<T>>('call_sidecar', {
+		path,
+		method,
+		body,
+		timeoutMs: options.timeoutMs,
+		requestId: options.requestId
+	});
+}
+
+/** Aborts a `callSidecar` made with the same `requestId`. */
+export function cancelSidecarRequest(requestId: string): Promise<boolean> {
+	return invoke<boolean>('cancel_sidecar_request', { requestId });
+}
+
+/**
+ * Whether a callSidecar() error means the queue was full; the
+ * `sidecar-queue-full` and `sidecar-queue-available` events track that too.
+ */
+export function isQueueFull(error: unknown): boolean {
+	return String(error).startsWith('QueueFull');
+}
+
+/** Number of callSidecar() requests waiting for their turn. */
+export function getQueueDepth(): Promise<number> {
+	return invoke<number>('get_queue_depth');
+}
+
+/** Preloads the models; resolves to whether they ended up ready. */
+export function warmupSidecar(): Promise<boolean> {
+	return invoke<boolean>('warmup_sidecar');
+}
+
+/** Sends `name: value` with every request the shell makes to the engine. */
+export function setSidecarHeader(name: string, value: string): Promise<void> {
+	return invoke('set_sidecar_header', { name, value });
+}
+
+/** Drops every header added by setSidecarHeader or NOMEN_SIDECAR_HEADERS. */
+export function clearSidecarHeaders(): Promise<void> {
+	return invoke('clear_sidecar_headers');
+}
+
+/** Switches the classifier model; it loads in the background. */
+export function setSidecarModel(name: string): Promise<string> {
+	return invoke<string>('set_sidecar_model', { name });
+}
+
+export function getSidecarModel(): Promise<string> {
+	return invoke<string>('get_sidecar_model');
+}
+
+export function listSidecarModels(): Promise<string[]> {
+	return invoke<string[]>('list_sidecar_models');
+}
+
+/** Switches the caption language; no model reload is involved. */
+export function setSidecarLanguage(code: string): Promise<string> {
+	return invoke<string>('set_sidecar_language', { code });
+}
+
+export function getSidecarLanguage(): Promise<string> {
+	return invoke<string>('get_sidecar_language');
+}
+
+/** Language codes the engine announced at startup; empty for older engines. */
+export function listSidecarLanguages(): Promise<string[]> {
+	return invoke<string[]>('list_sidecar_languages');
 }
@@ -29,5 +29,10 @@ if (Test-Path $targetDir) {
 
 Copy-Item -Recurse -Force "dist/nomen-sidecar/*" $targetDir
 
+# Checksum the shell verifies before launching (catches AV truncation)
+$binary = Join-Path $targetDir "nomen-sidecar.exe"
+$hash = (Get-FileHash -Algorithm SHA256 $binary).Hash.ToLower()
+Set-Content -NoNewline -Path (Join-Path $targetDir "nomen-sidecar.sha256") -Value $hash
+
 Write-Host "Copied sidecar to $targetDir" -ForegroundColor Green
 Write-Host "Sidecar ready for Tauri bundling" -ForegroundColor Cyan
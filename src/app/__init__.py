@@ -0,0 +1,15 @@
+VERSION = "0.1.0"
+
+# Optional features this build supports, announced in the startup handshake
+# so the frontend can hide what isn't there.
+CAPABILITIES = [
+    "classification",
+    "captioning",
+    "model_switching",
+    "cancellation",
+    "language_selection",
+]
+
+# Languages captions can be asked for, also announced in the handshake. The
+# captioner only writes English so far.
+LANGUAGES = ["en"]
@@ -1,16 +1,40 @@
-"""Entry point for ``python -m app`` — prints PORT before heavy imports."""
+"""Entry point for ``python -m app`` — prints the handshake before heavy imports."""
 
+import json
+import os
 import socket
+import sys
 
+from app import CAPABILITIES, LANGUAGES, VERSION
 
-def _find_open_port() -> int:
+
+# The shell passes the bind address explicitly and refuses to continue if
+# the sidecar turns out to be reachable from the LAN.
+host = os.environ.get("NOMEN_BIND_HOST", "127.0.0.1")
+
+
+def _find_open_port(requested: int = 0) -> int:
     with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as s:
-        s.bind(("127.0.0.1", 0))
+        s.bind((host, requested))
         return s.getsockname()[1]
 
 
-port = _find_open_port()
-print(f"PORT={port}", flush=True)
+if fixed_port := os.environ.get("NOMEN_SIDECAR_FIXED_PORT"):
+    port = int(fixed_port)
+    try:
+        _find_open_port(port)
+    except OSError:
+        # The shell retries on the next port up.
+        print(json.dumps({"status": "port_in_use", "port": port}), flush=True)
+        sys.exit(1)
+    handshake = {"port": port}
+else:
+    port = _find_open_port()
+    handshake = {"port": port}
+handshake.update(
+    pid=os.getpid(), version=VERSION, capabilities=CAPABILITIES, languages=LANGUAGES
+)
+print(json.dumps(handshake), flush=True)
 
 import uvicorn  # noqa: E402
 
@@ -19,4 +43,4 @@ from app.main import app  # noqa: E402
 
 paths.init()
 
-uvicorn.run(app, host="127.0.0.1", port=port, log_level="warning")
+uvicorn.run(app, host=host, port=port, log_level="warning")
@@ -1,5 +1,11 @@
 """NomenAudio FastAPI sidecar — entry point."""
 
+import asyncio
+import hmac
+import logging
+import os
+import re
+import signal
 from contextlib import asynccontextmanager
 
 import uvicorn
@@ -7,20 +13,23 @@ from fastapi import FastAPI
 from fastapi.middleware.cors import CORSMiddleware
 from fastapi.responses import JSONResponse
 
-from app import paths
+from app import VERSION, paths
 from app.db import repository
 from app.errors import AppError
 from app.ml import model_manager
 from app.routers import analysis as analysis_router
 from app.routers import files
 from app.routers import models as models_router
+from app.routers import pause as pause_router
+from app.routers import requests as requests_router
 from app.routers import settings as settings_router
+from app.routers import system as system_router
+from app.routers import transcribe as transcribe_router
 from app.routers import ucs as ucs_router
+from app.services import cancellation
 from app.services.settings import load_settings
 from app.ucs.engine import load_ucs
 
-VERSION = "0.1.0"
-
 
 @asynccontextmanager
 async def lifespan(app: FastAPI):
@@ -41,11 +50,70 @@ app.add_middleware(
     allow_headers=["*"],
 )
 
+# <audio> elements can't set headers, so playback alone may pass the token
+# in the query string.
+QUERY_TOKEN_PATH = re.compile(r"/files/[^/]+/audio")
+
+
+class _RedactToken(logging.Filter):
+    """Hide ``token=`` query values in uvicorn's access log lines."""
+
+    def filter(self, record: logging.LogRecord) -> bool:
+        if isinstance(record.args, tuple):
+            record.args = tuple(
+                re.sub(r"([?&]token=)[^&\s]*", r"\1***", arg) if isinstance(arg, str) else arg
+                for arg in record.args
+            )
+        return True
+
+
+logging.getLogger("uvicorn.access").addFilter(_RedactToken())
+
+
+@app.middleware("http")
+async def require_auth_token(request, call_next):
+    # The shell passes a per-spawn token; without one (tests, hand-started
+    # sidecars) every local caller is trusted as before.
+    token = os.environ.get("NOMEN_AUTH_TOKEN")
+    if token and request.method != "OPTIONS":
+        header = request.headers.get("authorization", "")
+        query = ""
+        if request.method == "GET" and QUERY_TOKEN_PATH.fullmatch(request.url.path):
+            query = request.query_params.get("token", "")
+        if not (
+            hmac.compare_digest(header, f"Bearer {token}")
+            or hmac.compare_digest(query, token)
+        ):
+            return JSONResponse(
+                status_code=401,
+                content={"error": "Missing or invalid auth token", "code": "UNAUTHORIZED"},
+            )
+    return await call_next(request)
+
+
+@app.middleware("http")
+async def clear_cancellation(request, call_next):
+    # Forget a request's cancel flag once it has finished either way.
+    request_id = request.headers.get(cancellation.REQUEST_ID_HEADER)
+    try:
+        return await call_next(request)
+    finally:
+        if request_id:
+            cancellation.clear(request_id)
+
+
 app.include_router(files.router)
 app.include_router(analysis_router.router)
 app.include_router(models_router.router)
+app.include_router(models_router.warmup_router)
+app.include_router(models_router.model_router)
+app.include_router(models_router.language_router)
 app.include_router(ucs_router.router)
 app.include_router(settings_router.router)
+app.include_router(requests_router.router)
+app.include_router(pause_router.router)
+app.include_router(system_router.router)
+app.include_router(transcribe_router.router)
 
 
 @app.exception_handler(AppError)
@@ -61,6 +129,14 @@ def health() -> dict[str, str]:
     return {"status": "ok", "version": VERSION}
 
 
+@app.post("/shutdown", status_code=202)
+async def shutdown() -> dict[str, str]:
+    # Stop uvicorn the way Ctrl+C does, once this response is out, so the
+    # lifespan teardown gets to run before the shell force-kills us.
+    asyncio.get_running_loop().call_later(0.1, signal.raise_signal, signal.SIGINT)
+    return {"status": "shutting down"}
+
+
 if __name__ == "__main__":
     # Prefer running via `python -m app` (__main__.py prints PORT before
     # heavy imports).  This fallback is kept for convenience / tests.
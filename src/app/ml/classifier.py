@@ -26,20 +26,21 @@ def _softmax(logits: "np.ndarray") -> "np.ndarray":
 class CLAPClassifier:
     """Wraps MS-CLAP 2023 for zero-shot classification of sound effects."""
 
-    def __init__(self) -> None:
+    def __init__(self, version: str = "2023") -> None:
+        self._version = version
         self._model = None
         self._text_embeddings: np.ndarray | None = None
         self._text_meta: list[dict] | None = None
 
     def load_model(self) -> None:
-        """Load CLAP 2023 model (CPU only) and apply librosa patch."""
+        """Load the CLAP model (CPU only) and apply librosa patch."""
         from msclap import CLAP
 
         from app.ml.clap_compat import patch_clap_audio
 
-        self._model = CLAP(version="2023", use_cuda=False)
+        self._model = CLAP(version=self._version, use_cuda=False)
         patch_clap_audio(self._model)
-        logger.info("CLAP 2023 model loaded")
+        logger.info("CLAP %s model loaded", self._version)
 
     def precompute_embeddings(self, phrases: list[str], meta: list[dict]) -> None:
         """Compute text embeddings for all label phrases."""
@@ -4,7 +4,7 @@ import logging
 import os
 import threading
 
-from app import paths
+from app import LANGUAGES, paths
 from app.ml.captioner import CLAPCaptioner
 from app.ml.classifier import CLAPClassifier
 
@@ -14,13 +14,19 @@ logger = logging.getLogger(__name__)
 # Module-level singletons
 # ---------------------------------------------------------------------------
 
+# CLAP checkpoints the classifier can run; the first is the default.
+AVAILABLE_MODELS = ("2023", "2022")
+
 _classifier: CLAPClassifier | None = None
 _captioner: CLAPCaptioner | None = None
 _loading: bool = False
 _ready: bool = False
 _error: str | None = None
 _status_message: str = ""
+_model_name: str = AVAILABLE_MODELS[0]
+_language: str = LANGUAGES[0]
 _lock = threading.Lock()
+_done = threading.Event()
 
 
 # ---------------------------------------------------------------------------
@@ -34,6 +40,7 @@ def start_loading() -> None:
     _loading = True
     _error = None
     _status_message = "Starting model load..."
+    _done.clear()
 
     thread = threading.Thread(target=_load_wrapper, daemon=True)
     thread.start()
@@ -78,6 +85,59 @@ def is_ready() -> bool:
         return _ready
 
 
+def get_model() -> str:
+    """Return the name of the active classifier checkpoint."""
+    return _model_name
+
+
+def set_model(name: str) -> None:
+    """Switch the classifier to checkpoint ``name`` and reload in the background.
+
+    Raises ValueError for an unknown name and RuntimeError while a load is
+    already running.
+    """
+    global _model_name, _classifier, _ready
+    if name not in AVAILABLE_MODELS:
+        raise ValueError(f"Unknown model: {name}")
+    with _lock:
+        if _loading:
+            raise RuntimeError("Models are still loading")
+        if name == _model_name:
+            return
+        _model_name = name
+        _classifier = None
+        _ready = False
+    start_loading()
+
+
+def get_language() -> str:
+    """Return the language captions are written in."""
+    return _language
+
+
+def set_language(code: str) -> None:
+    """Switch the caption language. Raises ValueError for an unsupported code."""
+    global _language
+    if code not in LANGUAGES:
+        raise ValueError(f"Unsupported language: {code} (supported: {', '.join(LANGUAGES)})")
+    _language = code
+
+
+def warm_up(timeout: float) -> bool:
+    """Block until models are loaded, (re)starting a load if none is running.
+
+    Returns whether they are ready, which is False on error or timeout.
+    """
+    with _lock:
+        if _ready:
+            return True
+        idle = not _loading
+    if idle:
+        start_loading()
+    _done.wait(timeout)
+    return is_ready()
+
+
 # ---------------------------------------------------------------------------
 # Background loading
 # ---------------------------------------------------------------------------
@@ -97,6 +157,14 @@ def _load_wrapper() -> None:
     finally:
         with _lock:
             _loading = False
+        _done.set()
+
+
+def _embeddings_filename(model: str) -> str:
+    """Cache file for ``model``'s text embeddings; the default keeps the old name."""
+    if model == AVAILABLE_MODELS[0]:
+        return "text_embeddings.npz"
+    return f"text_embeddings-{model}.npz"
 
 
 def _load_pipeline() -> None:
@@ -110,7 +178,7 @@ def _load_pipeline() -> None:
     )
 
     _status_message = "Loading CLAP model..."
-    classifier = CLAPClassifier()
+    classifier = CLAPClassifier(_model_name)
     classifier.load_model()
 
     _status_message = "Building UCS labels..."
@@ -120,7 +188,7 @@ def _load_pipeline() -> None:
 
     # Try loading from cache
     cache_dir = paths.get_cache_dir()
-    embeddings_file = os.path.join(cache_dir, "text_embeddings.npz")
+    embeddings_file = os.path.join(cache_dir, _embeddings_filename(_model_name))
     os.makedirs(cache_dir, exist_ok=True)
     if classifier.load_embeddings(embeddings_file, label_hash):
         _status_message = "Loaded cached embeddings"
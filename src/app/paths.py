@@ -2,6 +2,7 @@
 
 import os
 import sys
+import tempfile
 
 # Module-level cache
 _bundle_dir: str | None = None
@@ -25,6 +26,16 @@ def init() -> None:
         _bundle_dir = "data"
         _data_dir = "data"
 
+    # Intermediate files go where the shell asked, not the system temp dir.
+    if scratch := os.environ.get("NOMEN_SCRATCH_DIR"):
+        os.makedirs(scratch, exist_ok=True)
+        tempfile.tempdir = scratch
+
+
+def get_scratch_dir() -> str:
+    """Return the directory for intermediate files (``NOMEN_SCRATCH_DIR`` or the system temp dir)."""
+    return os.environ.get("NOMEN_SCRATCH_DIR") or tempfile.gettempdir()
+
 
 def get_db_path() -> str:
     """Return path to SQLite database file."""
@@ -22,6 +22,7 @@ from app.db.repository import (
 )
 from app.ml import model_manager
 from app.ml.suggestions import enrich_with_caption, generate_tier1_suggestions
+from app.services import pause
 from app.services.flagging import should_flag
 from app.services.settings import get_settings
 from app.models import (
@@ -290,6 +291,7 @@ async def _stream_analysis(
 
     for i, row in enumerate(rows):
         await asyncio.sleep(0)
+        await pause.wait_until_resumed()
         file_id = row["id"]
 
         yield _sse_event(
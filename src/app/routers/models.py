@@ -1,12 +1,22 @@
 """Model status endpoints."""
 
+import asyncio
+
 from pydantic import BaseModel
 
 from fastapi import APIRouter
 
+from app import LANGUAGES
+from app.errors import MODEL_NOT_READY, VALIDATION_ERROR, AppError
 from app.ml import model_manager
 
 router = APIRouter(prefix="/models", tags=["models"])
+warmup_router = APIRouter(tags=["models"])
+model_router = APIRouter(prefix="/model", tags=["models"])
+language_router = APIRouter(prefix="/language", tags=["models"])
+
+# How long POST /warmup waits for a cold load (first run computes embeddings).
+WARMUP_TIMEOUT_SECS = 600
 
 
 class ModelStatusResponse(BaseModel):
@@ -21,7 +31,89 @@ class ModelStatusResponse(BaseModel):
     status_message: str
 
 
+class ModelListResponse(BaseModel):
+    """Response body for GET /models."""
+
+    models: list[str]
+
+
+@router.get("", response_model=ModelListResponse)
+def list_models() -> ModelListResponse:
+    return ModelListResponse(models=list(model_manager.AVAILABLE_MODELS))
+
+
 @router.get("/status", response_model=ModelStatusResponse)
 def get_models_status() -> ModelStatusResponse:
     status = model_manager.get_status()
     return ModelStatusResponse(**status)
+
+
+class WarmupResponse(BaseModel):
+    """Response body for POST /warmup."""
+
+    ready: bool
+    error: str | None
+
+
+@warmup_router.post("/warmup", response_model=WarmupResponse)
+async def warmup() -> WarmupResponse:
+    """Preload the models, returning once they are ready (or failed)."""
+    ready = await asyncio.to_thread(model_manager.warm_up, WARMUP_TIMEOUT_SECS)
+    return WarmupResponse(ready=ready, error=model_manager.get_status()["error"])
+
+
+class ModelResponse(BaseModel):
+    """Response body for GET/POST /model."""
+
+    model: str
+
+
+class SetModelRequest(BaseModel):
+    """Request body for POST /model."""
+
+    name: str
+
+
+@model_router.get("", response_model=ModelResponse)
+def get_model() -> ModelResponse:
+    return ModelResponse(model=model_manager.get_model())
+
+
+@model_router.post("", response_model=ModelResponse)
+def set_model(body: SetModelRequest) -> ModelResponse:
+    """Switch the classifier checkpoint; the new one loads in the background."""
+    try:
+        model_manager.set_model(body.name)
+    except ValueError as e:
+        raise AppError(VALIDATION_ERROR, 422, str(e))
+    except RuntimeError as e:
+        raise AppError(MODEL_NOT_READY, 409, str(e))
+    return ModelResponse(model=model_manager.get_model())
+
+
+class LanguageResponse(BaseModel):
+    """Response body for GET/POST /language."""
+
+    language: str
+    languages: list[str]
+
+
+class SetLanguageRequest(BaseModel):
+    """Request body for POST /language."""
+
+    code: str
+
+
+@language_router.get("", response_model=LanguageResponse)
+def get_language() -> LanguageResponse:
+    return LanguageResponse(language=model_manager.get_language(), languages=list(LANGUAGES))
+
+
+@language_router.post("", response_model=LanguageResponse)
+def set_language(body: SetLanguageRequest) -> LanguageResponse:
+    """Switch the caption language; no model reload is needed."""
+    try:
+        model_manager.set_language(body.code)
+    except ValueError as e:
+        raise AppError(VALIDATION_ERROR, 422, str(e))
+    return LanguageResponse(language=model_manager.get_language(), languages=list(LANGUAGES))
@@ -0,0 +1,19 @@
+"""Pause and resume endpoints for background work."""
+
+from fastapi import APIRouter
+
+from app.services import pause
+
+router = APIRouter(tags=["pause"])
+
+
+@router.post("/pause")
+def pause_work() -> dict:
+    pause.pause()
+    return {"paused": True}
+
+
+@router.post("/resume")
+def resume_work() -> dict:
+    pause.resume()
+    return {"paused": False}
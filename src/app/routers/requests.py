@@ -0,0 +1,13 @@
+"""Abort endpoint for requests tagged with an ``X-Request-Id``."""
+
+from fastapi import APIRouter
+
+from app.services import cancellation
+
+router = APIRouter(prefix="/requests", tags=["requests"])
+
+
+@router.delete("/{request_id}", status_code=202)
+def cancel_request(request_id: str) -> dict:
+    cancellation.cancel(request_id)
+    return {"request_id": request_id, "cancelled": True}
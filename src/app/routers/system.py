@@ -0,0 +1,47 @@
+"""Host and runtime details for the shell's diagnostics."""
+
+import os
+import platform
+
+from pydantic import BaseModel
+
+from fastapi import APIRouter
+
+from app import VERSION
+
+router = APIRouter(prefix="/system", tags=["system"])
+
+
+class SystemInfoResponse(BaseModel):
+    """Response body for GET /system."""
+
+    version: str
+    python_version: str
+    platform: str
+    machine: str
+    cpu_count: int | None
+    torch_version: str | None
+    cuda_available: bool
+
+
+def _torch_info() -> tuple[str | None, bool]:
+    # Imported here so a sidecar without torch still answers.
+    try:
+        import torch
+    except ImportError:
+        return None, False
+    return torch.__version__, torch.cuda.is_available()
+
+
+@router.get("", response_model=SystemInfoResponse)
+def get_system_info() -> SystemInfoResponse:
+    torch_version, cuda_available = _torch_info()
+    return SystemInfoResponse(
+        version=VERSION,
+        python_version=platform.python_version(),
+        platform=platform.platform(),
+        machine=platform.machine(),
+        cpu_count=os.cpu_count(),
+        torch_version=torch_version,
+        cuda_available=cuda_available,
+    )
@@ -0,0 +1,57 @@
+"""Describe an uploaded audio file in words (clapcap caption)."""
+
+import asyncio
+import json
+import os
+import tempfile
+
+from fastapi import APIRouter, Request
+from pydantic import BaseModel
+
+from app.errors import VALIDATION_ERROR, AppError
+from app.ml import model_manager
+
+router = APIRouter(tags=["transcribe"])
+
+FILENAME_HEADER = "x-filename"
+OPTIONS_HEADER = "x-transcribe-options"
+
+
+class TranscribeResponse(BaseModel):
+    """Response body for POST /transcribe."""
+
+    text: str
+
+
+@router.post("/transcribe", response_model=TranscribeResponse)
+async def transcribe(request: Request) -> TranscribeResponse:
+    """Caption the raw audio in the request body.
+
+    The shell streams the file as ``application/octet-stream`` and names it
+    in ``X-Filename`` so the decoder sees the right extension. Options come
+    as JSON in ``X-Transcribe-Options``; none are used yet.
+    """
+    filename = request.headers.get(FILENAME_HEADER, "")
+    _parse_options(request.headers.get(OPTIONS_HEADER, "{}"))
+    suffix = os.path.splitext(filename)[1]
+    # Written to the scratch dir (tempfile's default once paths.init ran).
+    fd, path = tempfile.mkstemp(suffix=suffix)
+    try:
+        with os.fdopen(fd, "wb") as out:
+            async for chunk in request.stream():
+                out.write(chunk)
+        captioner = model_manager.get_captioner()
+        text = await asyncio.to_thread(captioner.caption, path)
+    finally:
+        os.remove(path)
+    return TranscribeResponse(text=text)
+
+
+def _parse_options(raw: str) -> dict:
+    try:
+        options = json.loads(raw)
+    except json.JSONDecodeError as exc:
+        raise AppError(VALIDATION_ERROR, 422, f"Invalid transcribe options: {exc}") from exc
+    if not isinstance(options, dict):
+        raise AppError(VALIDATION_ERROR, 422, "Transcribe options must be an object")
+    return options
@@ -0,0 +1,30 @@
+"""Cancellation flags for long-running requests.
+
+The shell tags proxied requests with an ``X-Request-Id`` header and calls
+``DELETE /requests/{id}`` to abort one. Long-running handlers poll
+``is_cancelled`` with their request's id and stop early when it is set.
+"""
+
+import threading
+
+REQUEST_ID_HEADER = "x-request-id"
+
+_lock = threading.Lock()
+_cancelled: set[str] = set()
+
+
+def cancel(request_id: str) -> None:
+    with _lock:
+        _cancelled.add(request_id)
+
+
+def is_cancelled(request_id: str | None) -> bool:
+    if request_id is None:
+        return False
+    with _lock:
+        return request_id in _cancelled
+
+
+def clear(request_id: str) -> None:
+    with _lock:
+        _cancelled.discard(request_id)
@@ -0,0 +1,32 @@
+"""Pause flag for background work while the app is in the background.
+
+With ``NOMEN_PAUSE_ON_BLUR`` the shell POSTs ``/pause`` when its window
+loses focus and ``/resume`` when it regains it. Long-running loops await
+``wait_until_resumed`` between units of work; a request in progress is
+never interrupted.
+"""
+
+import asyncio
+import threading
+
+# How often a paused loop re-checks the flag.
+POLL_INTERVAL_SECS = 0.2
+
+_paused = threading.Event()
+
+
+def pause() -> None:
+    _paused.set()
+
+
+def resume() -> None:
+    _paused.clear()
+
+
+def is_paused() -> bool:
+    return _paused.is_set()
+
+
+async def wait_until_resumed() -> None:
+    while _paused.is_set():
+        await asyncio.sleep(POLL_INTERVAL_SECS)
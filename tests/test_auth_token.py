@@ -0,0 +1,54 @@
+"""Tests for the per-spawn auth token middleware."""
+
+import logging
+from unittest.mock import AsyncMock, patch
+
+import pytest
+from fastapi.testclient import TestClient
+
+from app.main import _RedactToken, app
+
+
+client = TestClient(app)
+
+
+@pytest.fixture(autouse=True)
+def _token(monkeypatch) -> None:
+    monkeypatch.setenv("NOMEN_AUTH_TOKEN", "secret")
+
+
+def test_bearer_header_is_accepted() -> None:
+    resp = client.get("/health", headers={"Authorization": "Bearer secret"})
+    assert resp.status_code == 200
+
+
+def test_missing_token_is_rejected() -> None:
+    resp = client.get("/health")
+    assert resp.status_code == 401
+    assert resp.json()["code"] == "UNAUTHORIZED"
+
+
+def test_query_token_is_rejected_outside_audio() -> None:
+    resp = client.get("/health?token=secret")
+    assert resp.status_code == 401
+
+
+def test_query_token_is_accepted_for_audio() -> None:
+    with patch("app.routers.files.get_file", AsyncMock(return_value=None)):
+        resp = client.get("/files/abc/audio?token=secret")
+    assert resp.status_code == 404
+
+
+def test_access_log_redacts_token() -> None:
+    record = logging.LogRecord(
+        "uvicorn.access",
+        logging.INFO,
+        __file__,
+        0,
+        '%s - "%s %s HTTP/%s" %d',
+        ("127.0.0.1:5000", "GET", "/files/abc/audio?token=secret&t=1", "1.1", 200),
+        None,
+    )
+    _RedactToken().filter(record)
+    assert "secret" not in record.getMessage()
+    assert "token=***&t=1" in record.getMessage()
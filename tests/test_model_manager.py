@@ -16,6 +16,8 @@ def _reset_manager():
     model_manager._ready = False
     model_manager._error = None
     model_manager._status_message = ""
+    model_manager._model_name = model_manager.AVAILABLE_MODELS[0]
+    model_manager._language = model_manager.LANGUAGES[0]
     yield
     model_manager._classifier = None
     model_manager._captioner = None
@@ -23,6 +25,8 @@ def _reset_manager():
     model_manager._ready = False
     model_manager._error = None
     model_manager._status_message = ""
+    model_manager._model_name = model_manager.AVAILABLE_MODELS[0]
+    model_manager._language = model_manager.LANGUAGES[0]
 
 
 # ---------------------------------------------------------------------------
@@ -123,3 +127,72 @@ def test_get_captioner_reuses_instance(mock_cap_cls):
     cap = model_manager.get_captioner()
     assert cap is mock_instance
     mock_cap_cls.assert_not_called()
+
+
+# ---------------------------------------------------------------------------
+# set_model
+# ---------------------------------------------------------------------------
+
+
+def test_get_model_defaults_to_first_available():
+    assert model_manager.get_model() == model_manager.AVAILABLE_MODELS[0]
+
+
+@patch("app.ml.model_manager.start_loading")
+def test_set_model_switches_and_reloads(mock_start):
+    model_manager._classifier = MagicMock()
+    model_manager._ready = True
+
+    model_manager.set_model("2022")
+    assert model_manager.get_model() == "2022"
+    assert model_manager.is_ready() is False
+    mock_start.assert_called_once()
+
+
+@patch("app.ml.model_manager.start_loading")
+def test_set_model_same_name_is_noop(mock_start):
+    model_manager._ready = True
+    model_manager.set_model(model_manager.AVAILABLE_MODELS[0])
+    assert model_manager.is_ready() is True
+    mock_start.assert_not_called()
+
+
+def test_set_model_rejects_unknown_name():
+    with pytest.raises(ValueError, match="Unknown model"):
+        model_manager.set_model("nope")
+
+
+def test_set_model_refuses_while_loading():
+    model_manager._loading = True
+    with pytest.raises(RuntimeError, match="still loading"):
+        model_manager.set_model("2022")
+
+
+# ---------------------------------------------------------------------------
+# set_language
+# ---------------------------------------------------------------------------
+
+
+def test_get_language_defaults_to_first_supported():
+    assert model_manager.get_language() == model_manager.LANGUAGES[0]
+
+
+def test_set_language_switches_without_reloading():
+    with (
+        patch("app.ml.model_manager.LANGUAGES", ["en", "de"]),
+        patch("app.ml.model_manager.start_loading") as mock_start,
+    ):
+        model_manager.set_language("de")
+    assert model_manager.get_language() == "de"
+    mock_start.assert_not_called()
+
+
+def test_set_language_rejects_unsupported_code():
+    with pytest.raises(ValueError, match="Unsupported language: xx"):
+        model_manager.set_language("xx")
+    assert model_manager.get_language() == model_manager.LANGUAGES[0]
+
+
+def test_embeddings_filename_per_model():
+    assert model_manager._embeddings_filename("2023") == "text_embeddings.npz"
+    assert model_manager._embeddings_filename("2022") == "text_embeddings-2022.npz"
@@ -1,4 +1,4 @@
-"""Tests for the models status API endpoint."""
+"""Tests for the models API endpoints."""
 
 from unittest.mock import patch
 
@@ -81,3 +81,125 @@ async def test_models_status_error(client):
     assert resp.status_code == 200
     data = resp.json()
     assert data["error"] == "Failed to download model"
+
+
+# ---------------------------------------------------------------------------
+# POST /warmup
+# ---------------------------------------------------------------------------
+
+
+@pytest.mark.asyncio
+async def test_warmup_ready(client):
+    with (
+        patch("app.routers.models.model_manager.warm_up", return_value=True),
+        patch("app.routers.models.model_manager.get_status", return_value={"error": None}),
+    ):
+        resp = await client.post("/warmup")
+    assert resp.status_code == 200
+    assert resp.json() == {"ready": True, "error": None}
+
+
+@pytest.mark.asyncio
+async def test_warmup_reports_load_error(client):
+    with (
+        patch("app.routers.models.model_manager.warm_up", return_value=False),
+        patch(
+            "app.routers.models.model_manager.get_status",
+            return_value={"error": "Failed to download model"},
+        ),
+    ):
+        resp = await client.post("/warmup")
+    assert resp.status_code == 200
+    assert resp.json() == {"ready": False, "error": "Failed to download model"}
+
+
+# ---------------------------------------------------------------------------
+# GET /models, GET/POST /model
+# ---------------------------------------------------------------------------
+
+
+@pytest.mark.asyncio
+async def test_list_models(client):
+    with patch("app.routers.models.model_manager.AVAILABLE_MODELS", ("2023", "2022")):
+        resp = await client.get("/models")
+    assert resp.status_code == 200
+    assert resp.json() == {"models": ["2023", "2022"]}
+
+
+@pytest.mark.asyncio
+async def test_get_model(client):
+    with patch("app.routers.models.model_manager.get_model", return_value="2023"):
+        resp = await client.get("/model")
+    assert resp.status_code == 200
+    assert resp.json() == {"model": "2023"}
+
+
+@pytest.mark.asyncio
+async def test_set_model(client):
+    with (
+        patch("app.routers.models.model_manager.set_model") as mock_set,
+        patch("app.routers.models.model_manager.get_model", return_value="2022"),
+    ):
+        resp = await client.post("/model", json={"name": "2022"})
+    assert resp.status_code == 200
+    assert resp.json() == {"model": "2022"}
+    mock_set.assert_called_once_with("2022")
+
+
+@pytest.mark.asyncio
+async def test_set_model_unknown_name(client):
+    with patch(
+        "app.routers.models.model_manager.set_model",
+        side_effect=ValueError("Unknown model: nope"),
+    ):
+        resp = await client.post("/model", json={"name": "nope"})
+    assert resp.status_code == 422
+    assert resp.json()["code"] == "VALIDATION_ERROR"
+
+
+@pytest.mark.asyncio
+async def test_set_model_while_loading(client):
+    with patch(
+        "app.routers.models.model_manager.set_model",
+        side_effect=RuntimeError("Models are still loading"),
+    ):
+        resp = await client.post("/model", json={"name": "2022"})
+    assert resp.status_code == 409
+    assert resp.json()["error"] == "Models are still loading"
+
+
+# ---------------------------------------------------------------------------
+# GET/POST /language
+# ---------------------------------------------------------------------------
+
+
+@pytest.mark.asyncio
+async def test_get_language(client):
+    with (
+        patch("app.routers.models.model_manager.get_language", return_value="en"),
+        patch("app.routers.models.LANGUAGES", ["en", "de"]),
+    ):
+        resp = await client.get("/language")
+    assert resp.status_code == 200
+    assert resp.json() == {"language": "en", "languages": ["en", "de"]}
+
+
+@pytest.mark.asyncio
+async def test_set_language(client):
+    with (
+        patch("app.routers.models.model_manager.set_language") as mock_set,
+        patch("app.routers.models.model_manager.get_language", return_value="de"),
+        patch("app.routers.models.LANGUAGES", ["en", "de"]),
+    ):
+        resp = await client.post("/language", json={"code": "de"})
+    assert resp.status_code == 200
+    assert resp.json() == {"language": "de", "languages": ["en", "de"]}
+    mock_set.assert_called_once_with("de")
+
+
+@pytest.mark.asyncio
+async def test_set_language_unsupported_code(client):
+    resp = await client.post("/language", json={"code": "xx"})
+    assert resp.status_code == 422
+    assert resp.json()["code"] == "VALIDATION_ERROR"
+    assert "Unsupported language: xx" in resp.json()["error"]
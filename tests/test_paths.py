@@ -2,6 +2,7 @@
 
 import os
 import sys
+import tempfile
 from unittest.mock import patch
 
 import pytest
@@ -172,3 +173,25 @@ def test_get_path_before_init_raises():
 
     with pytest.raises(RuntimeError, match="paths.init\\(\\) not called"):
         paths.get_ucs_top_level()
+
+
+def test_scratch_dir_from_env(tmp_path):
+    """NOMEN_SCRATCH_DIR becomes the scratch dir and Python's temp dir."""
+    scratch = str(tmp_path / "scratch")
+    with (
+        patch.object(sys, "frozen", False, create=True),
+        patch.dict(os.environ, {"NOMEN_SCRATCH_DIR": scratch}),
+        patch("tempfile.tempdir", None),
+    ):
+        paths.init()
+        assert paths.get_scratch_dir() == scratch
+        assert os.path.isdir(scratch)
+        assert tempfile.gettempdir() == scratch
+
+
+def test_scratch_dir_defaults_to_system_temp():
+    """Without NOMEN_SCRATCH_DIR, the system temp dir is used."""
+    env = {k: v for k, v in os.environ.items() if k != "NOMEN_SCRATCH_DIR"}
+    with patch.object(sys, "frozen", False, create=True), patch.dict(os.environ, env, clear=True):
+        paths.init()
+        assert paths.get_scratch_dir() == tempfile.gettempdir()
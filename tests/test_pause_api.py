@@ -0,0 +1,43 @@
+"""Tests for the pause/resume endpoints."""
+
+import asyncio
+
+import pytest
+from fastapi.testclient import TestClient
+
+from app.main import app
+from app.services import pause
+
+
+client = TestClient(app)
+
+
+@pytest.fixture(autouse=True)
+def resumed():
+    pause.resume()
+    yield
+    pause.resume()
+
+
+def test_pause_sets_flag() -> None:
+    resp = client.post("/pause")
+    assert resp.status_code == 200
+    assert resp.json() == {"paused": True}
+    assert pause.is_paused()
+
+
+def test_resume_clears_flag() -> None:
+    client.post("/pause")
+    resp = client.post("/resume")
+    assert resp.json() == {"paused": False}
+    assert not pause.is_paused()
+
+
+@pytest.mark.asyncio
+async def test_wait_returns_once_resumed() -> None:
+    pause.pause()
+    waiter = asyncio.create_task(pause.wait_until_resumed())
+    await asyncio.sleep(0.05)
+    assert not waiter.done()
+    pause.resume()
+    await asyncio.wait_for(waiter, timeout=1)
@@ -0,0 +1,26 @@
+from fastapi.testclient import TestClient
+
+from app.main import app
+from app.services import cancellation
+
+
+client = TestClient(app)
+
+
+def test_delete_marks_request_cancelled() -> None:
+    resp = client.delete("/requests/abc123")
+    assert resp.status_code == 202
+    assert resp.json() == {"request_id": "abc123", "cancelled": True}
+    assert cancellation.is_cancelled("abc123")
+    cancellation.clear("abc123")
+
+
+def test_unknown_request_is_not_cancelled() -> None:
+    assert not cancellation.is_cancelled("never-seen")
+    assert not cancellation.is_cancelled(None)
+
+
+def test_finished_request_clears_its_flag() -> None:
+    cancellation.cancel("done-1")
+    client.get("/health", headers={"X-Request-Id": "done-1"})
+    assert not cancellation.is_cancelled("done-1")
@@ -0,0 +1,31 @@
+"""Tests for the system info endpoint."""
+
+import platform
+from unittest.mock import patch
+
+from fastapi.testclient import TestClient
+
+from app.main import app
+
+
+client = TestClient(app)
+
+
+def test_system_info_fields() -> None:
+    with patch("app.routers.system._torch_info", return_value=("2.5.0", True)):
+        resp = client.get("/system")
+    assert resp.status_code == 200
+    data = resp.json()
+    assert data["version"] == "0.1.0"
+    assert data["python_version"] == platform.python_version()
+    assert data["machine"] == platform.machine()
+    assert data["torch_version"] == "2.5.0"
+    assert data["cuda_available"] is True
+
+
+def test_system_info_without_torch() -> None:
+    with patch.dict("sys.modules", {"torch": None}):
+        resp = client.get("/system")
+    assert resp.status_code == 200
+    assert resp.json()["torch_version"] is None
+    assert resp.json()["cuda_available"] is False
@@ -0,0 +1,54 @@
+"""Tests for the transcribe endpoint."""
+
+import os
+from unittest.mock import MagicMock, patch
+
+from fastapi.testclient import TestClient
+
+from app.main import app
+
+
+client = TestClient(app)
+
+
+def _captioner(seen: dict) -> MagicMock:
+    def caption(path: str) -> str:
+        seen["path"] = path
+        with open(path, "rb") as f:
+            seen["body"] = f.read()
+        return "a dog barks twice"
+
+    captioner = MagicMock()
+    captioner.caption.side_effect = caption
+    return captioner
+
+
+def test_transcribe_returns_caption() -> None:
+    seen: dict = {}
+    with patch("app.ml.model_manager.get_captioner", return_value=_captioner(seen)):
+        resp = client.post(
+            "/transcribe",
+            content=b"RIFF....WAVE",
+            headers={"X-Filename": "bark.wav", "Content-Type": "application/octet-stream"},
+        )
+    assert resp.status_code == 200
+    assert resp.json() == {"text": "a dog barks twice"}
+    assert seen["body"] == b"RIFF....WAVE"
+    assert seen["path"].endswith(".wav")
+
+
+def test_transcribe_removes_temp_file() -> None:
+    seen: dict = {}
+    with patch("app.ml.model_manager.get_captioner", return_value=_captioner(seen)):
+        client.post("/transcribe", content=b"x", headers={"X-Filename": "a.flac"})
+    assert not os.path.exists(seen["path"])
+
+
+def test_transcribe_rejects_invalid_options() -> None:
+    resp = client.post(
+        "/transcribe",
+        content=b"x",
+        headers={"X-Filename": "a.wav", "X-Transcribe-Options": "not json"},
+    )
+    assert resp.status_code == 422
+    assert resp.json()["code"] == "VALIDATION_ERROR"